@@ -1,33 +1,98 @@
-use criterion::{criterion_group, criterion_main, Criterion};
-use rand::Rng;
-use week1::p2_vec::{baseline, vectorized};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::env;
+use support::{random_inputs, SEED};
+use week1::p2_vec::{
+    baseline, normalize, normalize_baseline, vec4_add, vec4_hsum, vectorized, Vec4,
+};
+
+mod support;
+
+fn seed() -> u64 {
+    env::var("SEED").map_or(SEED, |seed| seed.parse().expect("SEED must be a u64"))
+}
 
 fn criterion_benchmark(c: &mut Criterion) {
-    let mut rng = rand::thread_rng();
-    let inputs = (0..100)
-        .map(|_| {
-            [
-                rng.gen::<f64>(),
-                rng.gen::<f64>(),
-                rng.gen::<f64>(),
-                rng.gen::<f64>(),
-            ]
-        })
-        .collect::<Vec<_>>();
-    c.bench_function("baseline", |b| {
+    let seed = seed();
+    let inputs = random_inputs(seed, 100);
+    // The seed is part of each group's name, so results for different inputs aren't compared
+    let mut group = c.benchmark_group(format!("seed_{seed}"));
+    group.bench_function("baseline", |b| {
         b.iter(|| {
             for i in 0..99 {
                 baseline(inputs[i], inputs[i + 1]);
             }
         })
     });
-    c.bench_function("vectorized", |b| {
+    group.bench_function("vectorized", |b| {
         b.iter(|| {
             for i in 0..99 {
                 vectorized(inputs[i], inputs[i + 1]);
             }
         })
     });
+    // The same computations on f32s, which fit twice as many lanes in a vector register
+    let inputs_f32 = inputs
+        .iter()
+        .map(|v| v.map(|x| x as f32))
+        .collect::<Vec<_>>();
+    group.bench_function("baseline_f32", |b| {
+        b.iter(|| {
+            for i in 0..99 {
+                baseline(inputs_f32[i], inputs_f32[i + 1]);
+            }
+        })
+    });
+    group.bench_function("vectorized_f32", |b| {
+        b.iter(|| {
+            for i in 0..99 {
+                vectorized(inputs_f32[i], inputs_f32[i + 1]);
+            }
+        })
+    });
+    group.bench_function("normalize_baseline", |b| {
+        b.iter(|| {
+            for input in &inputs {
+                normalize_baseline(*input);
+            }
+        })
+    });
+    group.bench_function("normalize", |b| {
+        b.iter(|| {
+            for input in &inputs {
+                normalize(*input);
+            }
+        })
+    });
+    // Reducing each vector horizontally vs. accumulating point-wise and reducing once
+    group.bench_function("sum_horizontal", |b| {
+        b.iter(|| inputs.iter().map(|v| vec4_hsum(*v)).sum::<f64>())
+    });
+    group.bench_function("sum_vertical", |b| {
+        b.iter(|| vec4_hsum(inputs.iter().fold([0.; 4], |acc, v| vec4_add(acc, *v))))
+    });
+    group.finish();
+
+    // The benchmarks above fit in cache. These apply the same kernels across large arrays
+    // in structure-of-arrays layout, where each Vec4 is four consecutive elements of an array,
+    // so memory bandwidth matters as much as the arithmetic.
+    const N: usize = 1 << 20;
+    let mut rng = StdRng::seed_from_u64(seed.wrapping_add(1));
+    let a = (0..N).map(|_| rng.gen::<f64>()).collect::<Vec<_>>();
+    let b = (0..N).map(|_| rng.gen::<f64>()).collect::<Vec<_>>();
+    let mut out = vec![0.; N];
+    let mut apply = |f: fn(Vec4, Vec4) -> Vec4| {
+        let chunks = a.chunks_exact(4).zip(b.chunks_exact(4));
+        for (c, (a, b)) in out.chunks_exact_mut(4).zip(chunks) {
+            c.copy_from_slice(&f(a.try_into().unwrap(), b.try_into().unwrap()));
+        }
+    };
+
+    let mut group = c.benchmark_group(format!("soa_seed_{seed}"));
+    group.throughput(Throughput::Elements(N as u64));
+    group.bench_function("baseline", |bencher| bencher.iter(|| apply(baseline)));
+    group.bench_function("vectorized", |bencher| bencher.iter(|| apply(vectorized)));
+    group.finish();
 }
 
 criterion_group!(benches, criterion_benchmark);
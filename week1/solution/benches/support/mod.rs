@@ -0,0 +1,23 @@
+//! Input generation for the `p2_vec` benchmark, shared with `tests/bench_inputs.rs`.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use week1::p2_vec::Vec4;
+
+/// The default seed for generating inputs, so every run benchmarks the same data.
+/// It can be overridden by setting the `SEED` environment variable.
+pub const SEED: u64 = 0x5eed_f00d;
+
+/// Generates `n` random vectors from `seed`.
+pub fn random_inputs(seed: u64, n: usize) -> Vec<Vec4> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..n)
+        .map(|_| {
+            [
+                rng.gen::<f64>(),
+                rng.gen::<f64>(),
+                rng.gen::<f64>(),
+                rng.gen::<f64>(),
+            ]
+        })
+        .collect()
+}
@@ -1,4 +1,8 @@
-#![allow(clippy::needless_return, clippy::assign_op_pattern)]
+#![allow(
+    clippy::needless_return,
+    clippy::assign_op_pattern,
+    clippy::manual_is_multiple_of
+)]
 
 //! Welcome to Week 1 of the Rust Mini-course! This assignment
 //! will familiarize you with Rust's basic features as covered
@@ -38,6 +38,76 @@ pub fn collatz_iterative(n: usize) -> usize {
     return i;
 }
 
+/// Stopping times are only cached for numbers below this bound, to keep the cache's memory in check.
+const CACHE_SIZE: usize = 1 << 20;
+
+/// Computes the stopping time of `n`, using the stopping times of smaller numbers stored in `cache`.
+///
+/// A cache entry of 0 means the stopping time is unknown, except for 1 which is handled explicitly.
+/// Only the stopping time of `n` itself is cached.
+fn collatz_cached(n: usize, cache: &mut [usize]) -> usize {
+    let mut m = n;
+    let mut i = 0;
+    while m != 1 && (m >= cache.len() || cache[m] == 0) {
+        if m % 2 == 0 {
+            m /= 2;
+        } else {
+            m = 3 * m + 1;
+        }
+        i += 1;
+    }
+    if m != 1 {
+        i += cache[m];
+    }
+    if n < cache.len() {
+        cache[n] = i;
+    }
+    i
+}
+
+/// Lazily computes the stopping time `i` of each `n` in `1..=limit`, yielding the pairs `(n, i)`
+/// in order. The stopping times share a cache, so later numbers reuse the work done for earlier ones.
+pub fn collatz_lengths(limit: usize) -> impl Iterator<Item = (usize, usize)> {
+    let mut cache = vec![0; limit.min(CACHE_SIZE) + 1];
+    (1..=limit).map(move |n| (n, collatz_cached(n, &mut cache)))
+}
+
+/// Picks the pair `(n, i)` with the longest stopping time `i`, preferring the smaller `n` on ties.
+fn longer_stopping_time(a: (usize, usize), b: (usize, usize)) -> (usize, usize) {
+    if b.1 > a.1 || (b.1 == a.1 && b.0 < a.0) {
+        b
+    } else {
+        a
+    }
+}
+
+/// Finds the number `n` in `1..=limit` with the longest stopping time, returning `(n, i)`.
+/// If several numbers share the longest stopping time, the smallest is returned.
+///
+/// Panics if `limit` is 0.
+pub fn collatz_max_stopping_time(limit: usize) -> (usize, usize) {
+    assert!(limit >= 1, "limit must be at least 1");
+    collatz_lengths(limit).fold((1, 0), longer_stopping_time)
+}
+
+/// Computes the same result as [`collatz_max_stopping_time`], but splits the range across threads
+/// with rayon. Each rayon job keeps its own cache, so no memoization is shared between threads.
+///
+/// This function requires the `parallel` feature, e.g. `cargo test --features parallel`.
+#[cfg(feature = "parallel")]
+pub fn collatz_max_stopping_time_par(limit: usize) -> (usize, usize) {
+    use rayon::prelude::*;
+
+    assert!(limit >= 1, "limit must be at least 1");
+    (1..=limit)
+        .into_par_iter()
+        .map_init(
+            || vec![0; limit.min(CACHE_SIZE) + 1],
+            |cache, n| (n, collatz_cached(n, cache)),
+        )
+        .reduce(|| (1, 0), longer_stopping_time)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -64,4 +134,39 @@ mod test {
             assert_eq!(collatz_iterative(n), answer, "n = {}", n);
         }
     }
+
+    #[test]
+    fn collatz_lengths_test() {
+        let lengths = collatz_lengths(10).map(|(_, l)| l).collect::<Vec<_>>();
+        assert_eq!(lengths, COLLATZ_ANSWERS);
+
+        // The first number with a stopping time over 100, without computing the rest
+        let record = collatz_lengths(usize::MAX).find(|(_, l)| *l > 100);
+        assert_eq!(record, Some((27, 111)));
+        assert_eq!(collatz_lengths(0).count(), 0);
+    }
+
+    #[test]
+    fn collatz_max_stopping_time_test() {
+        assert_eq!(collatz_max_stopping_time(1), (1, 0));
+        assert_eq!(collatz_max_stopping_time(10), (9, 19));
+        assert_eq!(collatz_max_stopping_time(100), (97, 118));
+
+        let (n, i) = collatz_max_stopping_time(100000);
+        assert_eq!(collatz_iterative(n), i);
+        assert!((1..=100000).all(|m| collatz_iterative(m) <= i));
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn collatz_max_stopping_time_par_test() {
+        for limit in [1, 10, 100, 100000] {
+            assert_eq!(
+                collatz_max_stopping_time_par(limit),
+                collatz_max_stopping_time(limit),
+                "limit = {}",
+                limit
+            );
+        }
+    }
 }
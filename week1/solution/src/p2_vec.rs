@@ -6,30 +6,51 @@
 //! A vector contains the data, and the masks are used to implement control-flow without branching.
 
 use criterion::black_box;
+use std::ops::{Add, Mul};
 
-pub type Vec4 = [f64; 4];
+/// A vector of four floats, which are `f64`s unless otherwise specified.
+///
+/// Since `Vec4` is an array, a slice can be converted into one with the standard library's
+/// `TryFrom<&[T]>` impl for arrays, e.g. `Vec4::try_from(&data[i..i + 4])`, which fails
+/// if the slice doesn't have exactly four elements.
+pub type Vec4<F = f64> = [F; 4];
 pub type Mask4 = [bool; 4];
+/// A vector of four 32-bit integers.
+pub type IVec4 = [i32; 4];
+
+/// The floating-point types that the generic `Vec4` functions can operate on, i.e. `f32` and `f64`.
+pub trait Float: Copy + PartialOrd + Add<Output = Self> + Mul<Output = Self> {
+    const ZERO: Self;
+}
+
+impl Float for f32 {
+    const ZERO: Self = 0.;
+}
+
+impl Float for f64 {
+    const ZERO: Self = 0.;
+}
 
 // Problem 2a: implement the following Vec4 library functions.
 
 /// Adds two vectors together point-wise, i.e. a[i] + b[i]
 ///
 /// Run `cargo test vec4_add` to check your answer.
-pub fn vec4_add(a: Vec4, b: Vec4) -> Vec4 {
+pub fn vec4_add<F: Float>(a: Vec4<F>, b: Vec4<F>) -> Vec4<F> {
     [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]]
 }
 
 /// Multiplies two vectors together point-wise
 ///
 /// Run `cargo test vec4_mul` to check your answer.
-pub fn vec4_mul(a: Vec4, b: Vec4) -> Vec4 {
+pub fn vec4_mul<F: Float>(a: Vec4<F>, b: Vec4<F>) -> Vec4<F> {
     [a[0] * b[0], a[1] * b[1], a[2] * b[2], a[3] * b[3]]
 }
 
 /// Returns a vector v where v[i] = vtrue[i] if mask[i] is true, else v[i] = vfalse[i]
 ///
 /// Run `cargo test vec4_select` to check your answer.
-pub fn vec4_select(mask: Mask4, vtrue: Vec4, vfalse: Vec4) -> Vec4 {
+pub fn vec4_select<F: Float>(mask: Mask4, vtrue: Vec4<F>, vfalse: Vec4<F>) -> Vec4<F> {
     [
         if mask[0] { vtrue[0] } else { vfalse[0] },
         if mask[1] { vtrue[1] } else { vfalse[1] },
@@ -41,13 +62,162 @@ pub fn vec4_select(mask: Mask4, vtrue: Vec4, vfalse: Vec4) -> Vec4 {
 /// Returns a mask of whether a[i] > b[i]
 ///
 /// Run `cargo test vec4_gt` to check your answer.
-pub fn vec4_gt(a: Vec4, b: Vec4) -> Mask4 {
+pub fn vec4_gt<F: Float>(a: Vec4<F>, b: Vec4<F>) -> Mask4 {
     [a[0] > b[0], a[1] > b[1], a[2] > b[2], a[3] > b[3]]
 }
 
+/// Divides two vectors point-wise
+pub fn vec4_div(a: Vec4, b: Vec4) -> Vec4 {
+    [a[0] / b[0], a[1] / b[1], a[2] / b[2], a[3] / b[3]]
+}
+
+/// Takes the square root of each element of a vector
+pub fn vec4_sqrt(v: Vec4) -> Vec4 {
+    [v[0].sqrt(), v[1].sqrt(), v[2].sqrt(), v[3].sqrt()]
+}
+
+/// Takes the absolute value of each element of a vector
+pub fn vec4_abs(v: Vec4) -> Vec4 {
+    [v[0].abs(), v[1].abs(), v[2].abs(), v[3].abs()]
+}
+
+/// Returns the point-wise minimum of two vectors
+pub fn vec4_min(a: Vec4, b: Vec4) -> Vec4 {
+    [
+        a[0].min(b[0]),
+        a[1].min(b[1]),
+        a[2].min(b[2]),
+        a[3].min(b[3]),
+    ]
+}
+
+/// Returns the point-wise maximum of two vectors
+pub fn vec4_max(a: Vec4, b: Vec4) -> Vec4 {
+    [
+        a[0].max(b[0]),
+        a[1].max(b[1]),
+        a[2].max(b[2]),
+        a[3].max(b[3]),
+    ]
+}
+
+/// Restricts each element v[i] to the range [lo[i], hi[i]]
+pub fn vec4_clamp(v: Vec4, lo: Vec4, hi: Vec4) -> Vec4 {
+    vec4_min(vec4_max(v, lo), hi)
+}
+
+/// Sums the elements of a vector, i.e. v[0] + v[1] + v[2] + v[3]
+///
+/// Note that "horizontal" operations like this one combine elements *within* a vector,
+/// which SIMD instructions are not designed for. Prefer accumulating point-wise across many
+/// vectors and only reducing horizontally once at the end.
+pub fn vec4_hsum(v: Vec4) -> f64 {
+    (v[0] + v[1]) + (v[2] + v[3])
+}
+
+/// Returns the largest element of a vector
+pub fn vec4_hmax(v: Vec4) -> f64 {
+    v[0].max(v[1]).max(v[2].max(v[3]))
+}
+
+/// Returns the smallest element of a vector
+pub fn vec4_hmin(v: Vec4) -> f64 {
+    v[0].min(v[1]).min(v[2].min(v[3]))
+}
+
+/// Computes the dot product of two vectors, i.e. the sum of a[i] * b[i]
+pub fn vec4_dot(a: Vec4, b: Vec4) -> f64 {
+    vec4_hsum(vec4_mul(a, b))
+}
+
+/// Panics with a message naming the offending index if any index in `idx` is out of bounds.
+fn check_indices(idx: [usize; 4], len: usize) {
+    if let Some(i) = idx.iter().find(|i| **i >= len) {
+        panic!("index {i} in {idx:?} is out of bounds for a slice of length {len}");
+    }
+}
+
+/// Loads the elements at four (not necessarily contiguous) indices into a vector,
+/// i.e. v[i] = data[idx[i]].
+///
+/// Unlike loading four adjacent elements, the elements may be on different cache lines,
+/// so a gather can be much slower than a contiguous load.
+///
+/// Panics if any index is out of bounds.
+pub fn vec4_gather(data: &[f64], idx: [usize; 4]) -> Vec4 {
+    check_indices(idx, data.len());
+    [data[idx[0]], data[idx[1]], data[idx[2]], data[idx[3]]]
+}
+
+/// Stores the elements of a vector at four indices, i.e. data[idx[i]] = v[i]. This is the
+/// inverse of `vec4_gather`. If an index is repeated, the later element of `v` wins.
+///
+/// Panics if any index is out of bounds, in which case `data` is not modified.
+pub fn vec4_scatter(data: &mut [f64], idx: [usize; 4], v: Vec4) {
+    check_indices(idx, data.len());
+    for (i, x) in idx.into_iter().zip(v) {
+        data[i] = x;
+    }
+}
+
+/// Adds two integer vectors point-wise, wrapping around on overflow.
+///
+/// This matches SIMD integer instructions (e.g. x86's `paddd`), which silently wrap. Note that
+/// this differs from `a[i] + b[i]` on scalars, which panics on overflow in debug builds.
+pub fn ivec4_add(a: IVec4, b: IVec4) -> IVec4 {
+    ivec4_add_wrapping(a, b)
+}
+
+/// Adds two integer vectors point-wise, wrapping around on overflow.
+pub fn ivec4_add_wrapping(a: IVec4, b: IVec4) -> IVec4 {
+    [
+        a[0].wrapping_add(b[0]),
+        a[1].wrapping_add(b[1]),
+        a[2].wrapping_add(b[2]),
+        a[3].wrapping_add(b[3]),
+    ]
+}
+
+/// Adds two integer vectors point-wise, clamping to `i32::MIN` and `i32::MAX` on overflow.
+pub fn ivec4_add_saturating(a: IVec4, b: IVec4) -> IVec4 {
+    [
+        a[0].saturating_add(b[0]),
+        a[1].saturating_add(b[1]),
+        a[2].saturating_add(b[2]),
+        a[3].saturating_add(b[3]),
+    ]
+}
+
+/// Multiplies two integer vectors point-wise, wrapping around on overflow like [`ivec4_add`].
+pub fn ivec4_mul(a: IVec4, b: IVec4) -> IVec4 {
+    ivec4_mul_wrapping(a, b)
+}
+
+/// Multiplies two integer vectors point-wise, wrapping around on overflow.
+pub fn ivec4_mul_wrapping(a: IVec4, b: IVec4) -> IVec4 {
+    [
+        a[0].wrapping_mul(b[0]),
+        a[1].wrapping_mul(b[1]),
+        a[2].wrapping_mul(b[2]),
+        a[3].wrapping_mul(b[3]),
+    ]
+}
+
+/// Multiplies two integer vectors point-wise, clamping to `i32::MIN` and `i32::MAX` on overflow.
+pub fn ivec4_mul_saturating(a: IVec4, b: IVec4) -> IVec4 {
+    [
+        a[0].saturating_mul(b[0]),
+        a[1].saturating_mul(b[1]),
+        a[2].saturating_mul(b[2]),
+        a[3].saturating_mul(b[3]),
+    ]
+}
+
 /// Baseline computation written in traditional iterative style.
-pub fn baseline(a: Vec4, b: Vec4) -> Vec4 {
-    let mut c = [0.; 4];
+///
+/// This and `vectorized` are generic over the float width, so they can be compared for both `f32` and `f64`.
+pub fn baseline<F: Float>(a: Vec4<F>, b: Vec4<F>) -> Vec4<F> {
+    let mut c = [F::ZERO; 4];
     for i in 0..4 {
         // black_box so the compiler doesn't auto-vectorize this loop :-)
         if black_box(a[i] > b[i]) {
@@ -67,10 +237,37 @@ pub fn baseline(a: Vec4, b: Vec4) -> Vec4 {
 /// I get a ~2x speedup on my 2021 Macbook Pro.
 ///
 /// Run `cargo test vectorized` to check your answer.
-pub fn vectorized(a: Vec4, b: Vec4) -> Vec4 {
+pub fn vectorized<F: Float>(a: Vec4<F>, b: Vec4<F>) -> Vec4<F> {
     vec4_select(vec4_gt(a, b), vec4_mul(a, b), vec4_add(a, b))
 }
 
+/// Baseline normalization (dividing a vector by its magnitude) written in traditional iterative style.
+///
+/// The zero vector can't be scaled to a magnitude of 1, so it normalizes to the zero vector
+/// (rather than the NaNs from dividing by zero).
+pub fn normalize_baseline(v: Vec4) -> Vec4 {
+    let mut norm = 0.;
+    for x in v {
+        norm += black_box(x * x);
+    }
+    let norm = norm.sqrt();
+    let mut c = [0.; 4];
+    if norm == 0. {
+        return c;
+    }
+    for i in 0..4 {
+        c[i] = black_box(v[i] / norm);
+    }
+    return c;
+}
+
+/// Vectorized version of the baseline normalization, including its handling of the zero vector.
+/// This uses a select rather than a branch to handle it.
+pub fn normalize(v: Vec4) -> Vec4 {
+    let norm = vec4_sqrt([vec4_dot(v, v); 4]);
+    vec4_select(vec4_gt(norm, [0.; 4]), vec4_div(v, norm), [0.; 4])
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -103,4 +300,132 @@ mod test {
     fn test_vectorized() {
         assert_eq!(vectorized(A, B), baseline(A, B));
     }
+
+    #[test]
+    fn test_vectorized_f32() {
+        let a: Vec4<f32> = A.map(|x| x as f32);
+        let b: Vec4<f32> = B.map(|x| x as f32);
+        assert_eq!(vectorized(a, b), baseline(a, b));
+        assert_eq!(vectorized(a, b), vectorized(A, B).map(|x| x as f32));
+    }
+
+    #[test]
+    fn test_vec4_abs() {
+        assert_eq!(vec4_abs([-1., 0., -2.5, 3.]), [1., 0., 2.5, 3.]);
+    }
+
+    #[test]
+    fn test_vec4_min() {
+        assert_eq!(vec4_min(A, B), [0., 1., 2., 1.]);
+    }
+
+    #[test]
+    fn test_vec4_max() {
+        assert_eq!(vec4_max(A, B), [4., 3., 2., 3.]);
+    }
+
+    #[test]
+    fn test_vec4_clamp() {
+        assert_eq!(vec4_clamp(A, [1.; 4], [2.; 4]), [1., 1., 2., 2.]);
+        assert_eq!(vec4_clamp(B, [0.5; 4], [3.5; 4]), [3.5, 3., 2., 1.]);
+    }
+
+    #[test]
+    fn test_vec4_hsum() {
+        assert_eq!(vec4_hsum(A), 6.);
+        assert_eq!(vec4_hsum(B), 10.);
+    }
+
+    #[test]
+    fn test_vec4_hmax() {
+        assert_eq!(vec4_hmax(A), 3.);
+        assert_eq!(vec4_hmax(B), 4.);
+    }
+
+    #[test]
+    fn test_vec4_hmin() {
+        assert_eq!(vec4_hmin(A), 0.);
+        assert_eq!(vec4_hmin(B), 1.);
+    }
+
+    #[test]
+    fn test_vec4_dot() {
+        assert_eq!(vec4_dot(A, B), 10.);
+    }
+
+    #[test]
+    fn test_vec4_gather_scatter() {
+        let data = (0..10).map(|i| i as f64 * 1.5).collect::<Vec<_>>();
+        let idx = [7, 0, 9, 3];
+        let v = vec4_gather(&data, idx);
+        assert_eq!(v, [10.5, 0., 13.5, 4.5]);
+
+        let mut out = vec![0.; 10];
+        vec4_scatter(&mut out, idx, v);
+        for i in 0..10 {
+            let expected = if idx.contains(&i) { data[i] } else { 0. };
+            assert_eq!(out[i], expected);
+        }
+    }
+
+    #[test]
+    fn test_vec4_try_from_slice() {
+        let data = [0., 1., 2., 3., 4., 5., 6.];
+        assert_eq!(Vec4::try_from(&data[..4]).unwrap(), A);
+        assert!(Vec4::try_from(&data[4..]).is_err());
+
+        let sums = data
+            .chunks(4)
+            .map(|chunk| Vec4::try_from(chunk).map(vec4_hsum))
+            .collect::<Vec<_>>();
+        assert_eq!(sums[0].unwrap(), 6.);
+        assert!(sums[1].is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "index 4 in [0, 1, 4, 2] is out of bounds for a slice of length 4")]
+    fn test_vec4_gather_out_of_bounds() {
+        vec4_gather(&A, [0, 1, 4, 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_vec4_scatter_out_of_bounds() {
+        vec4_scatter(&mut [0.; 3], [0, 1, 2, 3], A);
+    }
+
+    #[test]
+    fn test_ivec4_add() {
+        let a: IVec4 = [1, -2, i32::MAX, i32::MIN];
+        let b: IVec4 = [2, 2, 1, -1];
+        assert_eq!(ivec4_add_saturating(a, b), [3, 0, i32::MAX, i32::MIN]);
+        assert_eq!(ivec4_add_wrapping(a, b), [3, 0, i32::MIN, i32::MAX]);
+        assert_eq!(ivec4_add(a, b), ivec4_add_wrapping(a, b));
+    }
+
+    #[test]
+    fn test_ivec4_mul() {
+        let a: IVec4 = [3, -4, i32::MAX, i32::MIN];
+        let b: IVec4 = [5, 2, 2, 2];
+        assert_eq!(ivec4_mul_saturating(a, b), [15, -8, i32::MAX, i32::MIN]);
+        assert_eq!(ivec4_mul_wrapping(a, b), [15, -8, -2, 0]);
+        assert_eq!(ivec4_mul(a, b), ivec4_mul_wrapping(a, b));
+    }
+
+    #[test]
+    fn test_normalize() {
+        for v in [A, B, [-1., 0.5, 3., -7.]] {
+            let n = normalize(v);
+            assert!((vec4_dot(n, n).sqrt() - 1.).abs() < 1e-9);
+            for (x, y) in n.iter().zip(normalize_baseline(v)) {
+                assert!((x - y).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_normalize_zero() {
+        assert_eq!(normalize([0.; 4]), [0.; 4]);
+        assert_eq!(normalize([0.; 4]), normalize_baseline([0.; 4]));
+    }
 }
@@ -0,0 +1,14 @@
+//! Checks that the inputs generated for the `p2_vec` benchmark are reproducible.
+
+#[path = "../benches/support/mod.rs"]
+mod support;
+
+use support::{random_inputs, SEED};
+
+#[test]
+fn test_bench_inputs_reproducible() {
+    let inputs = random_inputs(SEED, 100);
+    assert_eq!(inputs.len(), 100);
+    assert_eq!(inputs, random_inputs(SEED, 100));
+    assert_ne!(inputs, random_inputs(SEED + 1, 100));
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::doc_lazy_continuation)]
+
 /// P1: implementing basic algorithms
 pub mod p1_algos;
 
@@ -4,6 +4,9 @@
 //! Both of these problems involve the `Vec` datatype. I would take a look the `Vec` documentation:
 //! https://doc.rust-lang.org/std/vec/struct.Vec.html
 
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+
 /// P1a: `insort` is a function that takes a sorted vector `v`, and inserts an element `n` into `v`
 /// such that `v` remains sorted.
 ///
@@ -21,6 +24,19 @@ pub fn insort(v: &mut Vec<i32>, n: i32) {
   v.insert(j, n);
 }
 
+/// `insort_by` is like `insort`, except that `v` is sorted according to the comparator `cmp`
+/// (as with [`slice::sort_by`]) rather than ascending order. For example, `|a, b| b.cmp(a)`
+/// maintains a vector in descending order.
+///
+/// Like `insort`, `n` is inserted after any elements that compare equal to it.
+pub fn insort_by<T, F: Fn(&T, &T) -> Ordering>(v: &mut Vec<T>, n: T, cmp: F) {
+  let j = v
+    .iter()
+    .position(|n2| cmp(&n, n2) == Ordering::Less)
+    .unwrap_or(v.len());
+  v.insert(j, n);
+}
+
 type Node = i32;
 
 /// P1b: `connected` is a function that takes an edge-list representation `edges` of a *directed* graph
@@ -45,7 +61,7 @@ pub fn connected(edges: &[(&Node, &Node)], src: &Node, dst: &Node) -> bool {
   loop {
     let size = reachable.len();
     for (u, v) in edges {
-      if contains(&reachable, *u) && !contains(&reachable, *v) {
+      if contains(&reachable, u) && !contains(&reachable, v) {
         reachable.push(*v);
       }
     }
@@ -58,6 +74,27 @@ pub fn connected(edges: &[(&Node, &Node)], src: &Node, dst: &Node) -> bool {
   contains(&reachable, dst)
 }
 
+/// `connected_adj` is like `connected`, but for a directed graph whose nodes are identified by
+/// index. `adj` maps each node to the nodes it has an edge to, and a node with no outgoing edges
+/// can be left out of the map. A node is always connected to itself.
+///
+/// Nodes are visited in breadth-first order, and each node is visited at most once.
+pub fn connected_adj(adj: &HashMap<usize, Vec<usize>>, src: usize, dst: usize) -> bool {
+  let mut visited = HashSet::from([src]);
+  let mut queue = VecDeque::from([src]);
+  while let Some(u) = queue.pop_front() {
+    if u == dst {
+      return true;
+    }
+    for &v in adj.get(&u).into_iter().flatten() {
+      if visited.insert(v) {
+        queue.push_back(v);
+      }
+    }
+  }
+  false
+}
+
 #[cfg(test)]
 mod test {
   use super::*;
@@ -76,11 +113,58 @@ mod test {
     assert_eq!(v, vec![0, 1, 3, 5, 8, 9]);
   }
 
+  #[test]
+  fn insort_by_descending_test() {
+    let mut v = vec![8, 5, 1];
+    let desc = |a: &i32, b: &i32| b.cmp(a);
+
+    insort_by(&mut v, 9, desc);
+    assert_eq!(v, vec![9, 8, 5, 1]);
+
+    insort_by(&mut v, 3, desc);
+    assert_eq!(v, vec![9, 8, 5, 3, 1]);
+
+    insort_by(&mut v, 0, desc);
+    assert_eq!(v, vec![9, 8, 5, 3, 1, 0]);
+  }
+
+  #[test]
+  fn insort_by_key_test() {
+    #[derive(Debug, PartialEq)]
+    struct Person {
+      name: &'static str,
+      age: u32,
+    }
+
+    let by_age = |a: &Person, b: &Person| a.age.cmp(&b.age);
+    let mut v = Vec::new();
+    insort_by(&mut v, Person { name: "Ann", age: 40 }, by_age);
+    insort_by(&mut v, Person { name: "Bob", age: 25 }, by_age);
+    insort_by(&mut v, Person { name: "Cat", age: 40 }, by_age);
+    insort_by(&mut v, Person { name: "Dan", age: 31 }, by_age);
+
+    let names = v.iter().map(|p| p.name).collect::<Vec<_>>();
+    assert_eq!(names, vec!["Bob", "Dan", "Ann", "Cat"]);
+  }
+
   #[test]
   fn connected_test() {
-    let nodes = vec![1, 1, 1];
+    let nodes = [1, 1, 1];
     let edges = vec![(&nodes[0], &nodes[1]), (&nodes[1], &nodes[2])];
     assert!(connected(&edges, &nodes[0], &nodes[2]));
     assert!(!connected(&edges, &nodes[2], &nodes[0]))
   }
+
+  #[test]
+  fn connected_adj_test() {
+    // 0 -> 1 -> 2 -> 0 is a cycle, 3 -> 1 is a one-way edge, and 4 has no edges at all
+    let adj = HashMap::from([(0, vec![1]), (1, vec![2]), (2, vec![0]), (3, vec![1])]);
+    assert!(connected_adj(&adj, 0, 2));
+    assert!(connected_adj(&adj, 2, 1));
+    assert!(connected_adj(&adj, 3, 0));
+    assert!(!connected_adj(&adj, 0, 3));
+    assert!(!connected_adj(&adj, 0, 4));
+    assert!(!connected_adj(&adj, 4, 0));
+    assert!(connected_adj(&adj, 4, 4));
+  }
 }
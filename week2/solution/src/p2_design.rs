@@ -106,3 +106,65 @@ fn test_fill_progress_bar() {
   fill_progress_bar(&mut buf, ('[', ']'), 0.2);
   assert_eq!(buf, "[==        ]");
 }
+
+/// The ways that a fraction passed to `fill_progress_bar_checked` can be invalid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProgressError {
+  /// The fraction was NaN.
+  NotANumber,
+  /// The fraction was outside of [0, 1].
+  OutOfRange(f32),
+}
+
+impl std::fmt::Display for ProgressError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ProgressError::NotANumber => write!(f, "progress fraction is NaN"),
+      ProgressError::OutOfRange(frac) => write!(f, "progress fraction {frac} is not in [0, 1]"),
+    }
+  }
+}
+
+impl std::error::Error for ProgressError {}
+
+/// fill_progress_bar_checked is like fill_progress_bar, except that it returns an error
+/// rather than clamping when `frac` is NaN or outside of [0, 1]. `buf` is left unchanged on error.
+pub fn fill_progress_bar_checked(
+  // Same parameters as fill_progress_bar, for the same reasons.
+  buf: &mut String,
+  delims: (char, char),
+  frac: f32,
+) -> Result<(), ProgressError> // could be a bool or an Option<()>, but a Result with a dedicated error type
+                               // lets the caller tell a NaN apart from an out-of-range value, and works with `?`.
+{
+  if frac.is_nan() {
+    return Err(ProgressError::NotANumber);
+  }
+  if !(0. ..=1.).contains(&frac) {
+    return Err(ProgressError::OutOfRange(frac));
+  }
+  fill_progress_bar(buf, delims, frac);
+  Ok(())
+}
+
+#[test]
+fn test_fill_progress_bar_checked() {
+  let mut buf = String::new();
+  assert_eq!(fill_progress_bar_checked(&mut buf, ('[', ']'), 0.2), Ok(()));
+  assert_eq!(buf, "[==        ]");
+
+  let mut buf = String::new();
+  assert_eq!(
+    fill_progress_bar_checked(&mut buf, ('[', ']'), f32::NAN),
+    Err(ProgressError::NotANumber)
+  );
+  assert_eq!(
+    fill_progress_bar_checked(&mut buf, ('[', ']'), -0.5),
+    Err(ProgressError::OutOfRange(-0.5))
+  );
+  assert_eq!(
+    fill_progress_bar_checked(&mut buf, ('[', ']'), 1.5),
+    Err(ProgressError::OutOfRange(1.5))
+  );
+  assert!(buf.is_empty());
+}
@@ -0,0 +1,301 @@
+//! Code shared by the starter and solution versions of `p1_bst.rs`.
+//!
+//! Both crates include this file as a submodule with a `#[path]` attribute,
+//! so the tree type, its pretty-printer and the common tests are written once
+//! and only the exercise method bodies differ between the two crates.
+
+use std::fmt::{self, Debug, Display};
+
+/// Equality and hashing are both structural: two trees holding the same elements
+/// in a different shape are neither equal nor guaranteed to hash the same.
+/// Compare `Vec::from(tree)` if you want to compare trees by their contents.
+#[derive(PartialEq, Eq, Hash, Clone, Default)]
+pub enum BST<T> {
+    /// A leaf is the bottom of the tree, it contains no data
+    #[default]
+    Leaf,
+    /// A node contains a datum of type T, then left and right children.
+    /// Those children are in boxes, so the BST type has a fixed size.
+    Node(T, Box<BST<T>>, Box<BST<T>>),
+}
+
+impl<T: PartialOrd + Display> BST<T> {
+    /// Provided helper function that gives a nice visual representation of a BST.
+    /// You can print any BST by doing `println!("{tree:?}")`.
+    ///
+    /// Adapted from https://docs.rs/ascii_tree
+    ///
+    /// Values are right-aligned to `width` characters so that the columns line up
+    /// even when the values have different lengths.
+    fn fmt_levels(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        level: Vec<usize>,
+        width: usize,
+    ) -> fmt::Result {
+        use BST::*;
+        const EMPTY: &str = "   ";
+        const EDGE: &str = " └─";
+        const PIPE: &str = " │ ";
+        const BRANCH: &str = " ├─";
+
+        let maxpos = level.len();
+        let mut second_line = String::new();
+        for (pos, l) in level.iter().enumerate() {
+            let last_row = pos == maxpos - 1;
+            if *l == 1 {
+                if !last_row {
+                    write!(f, "{}", EMPTY)?
+                } else {
+                    write!(f, "{}", EDGE)?
+                }
+                second_line.push_str(EMPTY);
+            } else {
+                if !last_row {
+                    write!(f, "{}", PIPE)?
+                } else {
+                    write!(f, "{}", BRANCH)?
+                }
+                second_line.push_str(PIPE);
+            }
+        }
+
+        match self {
+            Node(s, l, r) => {
+                let mut d = 2;
+                writeln!(f, " {:>width$}", s.to_string())?;
+                for t in &[l, r] {
+                    let mut lnext = level.clone();
+                    lnext.push(d);
+                    d -= 1;
+                    t.fmt_levels(f, lnext, width)?;
+                }
+            }
+            Leaf => writeln!(f)?,
+        }
+        Ok(())
+    }
+
+    /// Computes the length of the widest value in the BST when displayed.
+    fn max_width(&self) -> usize {
+        match self {
+            BST::Node(s, l, r) => {
+                let w = s.to_string().chars().count();
+                w.max(l.max_width()).max(r.max_width())
+            }
+            BST::Leaf => 0,
+        }
+    }
+}
+
+impl<T: Debug + Display + PartialOrd> fmt::Debug for BST<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_levels(f, vec![], self.max_width())
+    }
+}
+
+/// Displays a BST on one line in a Lisp-like form: a node is `(value left right)`,
+/// and a leaf is `.`. For example, a node `B` with leaf children `A` and `C` is
+/// displayed as `(B (A . .) (C . .))`.
+impl<T: Display> fmt::Display for BST<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BST::Node(s, l, r) => write!(f, "({s} {l} {r})"),
+            BST::Leaf => write!(f, "."),
+        }
+    }
+}
+
+#[cfg(test)]
+lazy_static::lazy_static! {
+    pub(crate) static ref TEST_TREE: BST<&'static str> = {
+        use BST::*;
+        Node(
+            "B",
+            Box::new(Node("A", Box::new(Leaf), Box::new(Leaf))),
+            Box::new(Node("C", Box::new(Leaf), Box::new(Leaf))),
+        )
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use BST::*;
+
+    #[test]
+    fn len_test() {
+        let heap_tree: Box<BST<i32>> = Box::new(BST::Leaf);
+        match &*heap_tree {
+            BST::Leaf => {}
+            BST::Node(t, _, _) => {
+                println!("{}", t);
+            }
+        };
+
+        assert_eq!(TEST_TREE.len(), 3);
+    }
+
+    #[test]
+    fn debug_test() {
+        let t = Node(
+            100,
+            Box::new(Node(5, Box::new(Leaf), Box::new(Leaf))),
+            Box::new(Node(
+                250,
+                Box::new(Node(7, Box::new(Leaf), Box::new(Leaf))),
+                Box::new(Leaf),
+            )),
+        );
+        let expected = [
+            " 100",
+            " ├─   5",
+            " │  ├─",
+            " │  └─",
+            " └─ 250",
+            "    ├─   7",
+            "    │  ├─",
+            "    │  └─",
+            "    └─",
+        ];
+        assert_eq!(format!("{t:?}"), expected.join("\n") + "\n");
+    }
+
+    #[test]
+    fn display_test() {
+        assert_eq!(TEST_TREE.to_string(), "(B (A . .) (C . .))");
+        assert_eq!(BST::<i32>::Leaf.to_string(), ".");
+
+        let t = Node(
+            10,
+            Box::new(Leaf),
+            Box::new(Node(
+                20,
+                Box::new(Node(15, Box::new(Leaf), Box::new(Leaf))),
+                Box::new(Leaf),
+            )),
+        );
+        assert_eq!(format!("{t}"), "(10 . (20 (15 . .) .))");
+    }
+
+    #[test]
+    fn insertion_test() {
+        let mut t = TEST_TREE.clone();
+        t.insert("E");
+        assert_eq!(
+            t,
+            Node(
+                "B",
+                Box::new(Node("A", Box::new(Leaf), Box::new(Leaf))),
+                Box::new(Node(
+                    "C",
+                    Box::new(Leaf),
+                    Box::new(Node("E", Box::new(Leaf), Box::new(Leaf)))
+                )),
+            )
+        );
+    }
+
+    #[test]
+    fn search_test() {
+        let mut t = TEST_TREE.clone();
+        t.insert("E");
+        assert!(t.search(&"D") == Some(&"E"));
+        assert!(t.search(&"C") == Some(&"C"));
+        assert!(t.search(&"F").is_none());
+    }
+
+    #[test]
+    fn rebalance1_test() {
+        let mut t = Node(
+            "D",
+            Box::new(Node(
+                "B",
+                Box::new(Node("A", Box::new(Leaf), Box::new(Leaf))),
+                Box::new(Node("C", Box::new(Leaf), Box::new(Leaf))),
+            )),
+            Box::new(Node("E", Box::new(Leaf), Box::new(Leaf))),
+        );
+
+        let t2 = Node(
+            "C",
+            Box::new(Node(
+                "B",
+                Box::new(Node("A", Box::new(Leaf), Box::new(Leaf))),
+                Box::new(Leaf),
+            )),
+            Box::new(Node(
+                "D",
+                Box::new(Leaf),
+                Box::new(Node("E", Box::new(Leaf), Box::new(Leaf))),
+            )),
+        );
+
+        t.rebalance();
+        assert_eq!(t, t2);
+    }
+
+    #[test]
+    fn rebalance2_test() {
+        let mut t = Node(
+            "A",
+            Box::new(Leaf),
+            Box::new(Node(
+                "B",
+                Box::new(Leaf),
+                Box::new(Node(
+                    "C",
+                    Box::new(Leaf),
+                    Box::new(Node("D", Box::new(Leaf), Box::new(Leaf))),
+                )),
+            )),
+        );
+
+        let t2 = Node(
+            "B",
+            Box::new(Node("A", Box::new(Leaf), Box::new(Leaf))),
+            Box::new(Node(
+                "C",
+                Box::new(Leaf),
+                Box::new(Node("D", Box::new(Leaf), Box::new(Leaf))),
+            )),
+        );
+
+        t.rebalance();
+        assert_eq!(t, t2);
+    }
+
+    #[test]
+    fn rebalance3_test() {
+        let mut t = Node(
+            "E",
+            Box::new(Node(
+                "B",
+                Box::new(Leaf),
+                Box::new(Node(
+                    "D",
+                    Box::new(Node("C", Box::new(Leaf), Box::new(Leaf))),
+                    Box::new(Leaf),
+                )),
+            )),
+            Box::new(Node("F", Box::new(Leaf), Box::new(Leaf))),
+        );
+
+        let t2 = Node(
+            "D",
+            Box::new(Node(
+                "B",
+                Box::new(Leaf),
+                Box::new(Node("C", Box::new(Leaf), Box::new(Leaf))),
+            )),
+            Box::new(Node(
+                "E",
+                Box::new(Leaf),
+                Box::new(Node("F", Box::new(Leaf), Box::new(Leaf))),
+            )),
+        );
+
+        t.rebalance();
+        assert_eq!(t, t2);
+    }
+}
@@ -16,9 +16,54 @@ fn criterion_benchmark(c: &mut Criterion) {
             }
         }
 
-        let img = Image::load(path).unwrap();
+        let img: Image = Image::load(path).unwrap();
         group.bench_function(path, |b| b.iter(|| img.clone().carve()));
     }
+    group.finish();
+
+    // Compare storing energies in a HashMap against storing them in a flat vector
+    let mut group = c.benchmark_group("storage");
+    group.sample_size(10);
+
+    let path = "castle.jpg";
+    if env::var("BENCH").map_or(true, |target| path.contains(&target)) {
+        let img: Image = Image::load(path).unwrap();
+        group.bench_function("hashmap", |b| b.iter(|| img.carve_hashmap()));
+        group.bench_function("vec", |b| b.iter(|| img.carve()));
+    }
+    group.finish();
+
+    // Compare recomputing the energy after every seam against updating it incrementally
+    let mut group = c.benchmark_group("incremental");
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(20));
+
+    let path = "landscape.jpeg";
+    if env::var("BENCH").map_or(true, |target| path.contains(&target)) {
+        const SEAMS: usize = 5;
+        let img: Image = Image::load(path).unwrap();
+        group.bench_function("full", |b| {
+            b.iter(|| (0..SEAMS).fold(img.clone(), |img, _| img.carve()))
+        });
+        group.bench_function("incremental", |b| b.iter(|| img.carve_incremental(SEAMS)));
+        group.bench_function("double_buffered", |b| {
+            b.iter(|| img.carve_double_buffered(SEAMS))
+        });
+    }
+    group.finish();
+
+    // Compare the scalar and vectorized initial energy computations
+    #[cfg(feature = "simd")]
+    {
+        let mut group = c.benchmark_group("simd");
+        let path = "vaporwave.jpeg";
+        if env::var("BENCH").map_or(true, |target| path.contains(&target)) {
+            let img: Image = Image::load(path).unwrap();
+            group.bench_function("scalar", |b| b.iter(|| img.compute_initial_energy()));
+            group.bench_function("simd", |b| b.iter(|| img.compute_initial_energy_simd()));
+        }
+        group.finish();
+    }
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -1,24 +1,130 @@
-use std::env;
+use std::{env, process};
+use week3::p2_img::Image;
 
-fn main() {
-    let mut args = env::args().skip(1);
+const USAGE: &str =
+    "Usage: p2_img_bin <path> [count] [--vertical <count>] [--horizontal <count>] [--both <count>]";
+
+/// The command-line arguments: an image path and how many seams to carve in each direction.
+#[derive(Debug, PartialEq, Eq)]
+struct Args {
+    path: String,
+    vertical: usize,
+    horizontal: usize,
+}
+
+/// Parses the arguments after the program name.
+///
+/// A bare count carves that many vertical seams, for compatibility with earlier versions.
+/// Without any counts, 50 vertical seams are carved.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut args = args.into_iter();
     let path = args
         .next()
-        .expect("You have to provide a path to an image to process");
-    let iters = match args.next() {
-        Some(n) => n
-            .parse::<usize>()
-            .expect("Number of iterations must be a numeral string"),
-        None => 50,
+        .ok_or("You have to provide a path to an image to process")?;
+    let parse_count = |n: Option<String>| -> Result<usize, String> {
+        let n = n.ok_or("Expected a number of seams after the flag")?;
+        n.parse::<usize>()
+            .map_err(|_| format!("Number of seams must be a numeral string, got {n:?}"))
     };
 
-    let mut img = week3::p2_img::Image::load(path).unwrap();
+    let (mut vertical, mut horizontal) = (None, 0);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--vertical" => vertical = Some(parse_count(args.next())?),
+            "--horizontal" => horizontal = parse_count(args.next())?,
+            "--both" => {
+                let n = parse_count(args.next())?;
+                vertical = Some(n);
+                horizontal = n;
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown flag {arg}\n{USAGE}")),
+            _ => vertical = Some(parse_count(Some(arg))?),
+        }
+    }
+
+    let vertical = match (vertical, horizontal) {
+        (Some(n), _) => n,
+        (None, 0) => 50,
+        (None, _) => 0,
+    };
+    Ok(Args {
+        path,
+        vertical,
+        horizontal,
+    })
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {e}");
+        process::exit(1);
+    }
+}
+
+/// Parses the arguments, then carves the image and saves it to `output.jpg`.
+fn run() -> week_error::Result<()> {
+    let args = parse_args(env::args().skip(1))?;
 
-    for i in 0..iters {
-        println!("Iteration {i}");
-        img = img.carve();
+    let mut img: Image = Image::load(&args.path)?;
+    if args.vertical >= img.width() || args.horizontal >= img.height() {
+        return Err(format!(
+            "Cannot carve {} vertical and {} horizontal seams from a {}x{} image",
+            args.vertical,
+            args.horizontal,
+            img.width(),
+            img.height()
+        )
+        .into());
     }
 
-    img.save("output.jpg").unwrap();
+    println!("Carving {} vertical seams", args.vertical);
+    img = img.carve_to_width(img.width() - args.vertical);
+
+    for i in 0..args.horizontal {
+        println!("Horizontal iteration {i}");
+        img = img.carve_horizontal();
+    }
+
+    img.save("output.jpg")?;
     println!("Done!");
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn args(vertical: usize, horizontal: usize) -> Args {
+        Args {
+            path: "castle.jpg".into(),
+            vertical,
+            horizontal,
+        }
+    }
+
+    #[test]
+    fn parse_args_test() {
+        assert_eq!(parse(&["castle.jpg"]), Ok(args(50, 0)));
+        assert_eq!(parse(&["castle.jpg", "10"]), Ok(args(10, 0)));
+        assert_eq!(parse(&["castle.jpg", "--vertical", "5"]), Ok(args(5, 0)));
+        assert_eq!(parse(&["castle.jpg", "--horizontal", "7"]), Ok(args(0, 7)));
+        assert_eq!(
+            parse(&["castle.jpg", "--vertical", "5", "--horizontal", "7"]),
+            Ok(args(5, 7))
+        );
+        assert_eq!(parse(&["castle.jpg", "--both", "3"]), Ok(args(3, 3)));
+        assert_eq!(
+            parse(&["castle.jpg", "--both", "3", "--horizontal", "1"]),
+            Ok(args(3, 1))
+        );
+
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["castle.jpg", "--vertical"]).is_err());
+        assert!(parse(&["castle.jpg", "--horizontal", "many"]).is_err());
+        assert!(parse(&["castle.jpg", "--diagonal", "1"]).is_err());
+    }
 }
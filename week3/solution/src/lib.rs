@@ -1,7 +1,8 @@
-#![allow(clippy::len_without_is_empty)]
-
 /// P1: binary search tree implementation
 pub mod p1_bst;
 
 /// P2: optimizing image seam carving
 pub mod p2_img;
+
+/// P3: persistent binary search tree
+pub mod p3_persistent;
@@ -21,17 +21,13 @@
 //!   Box::new(Node("D", 
 //!     Box::new(Leaf), Box::new(Leaf))));
 
-use std::fmt::{self, Debug, Display};
+use std::fmt::{self, Display};
 use std::mem;
+use std::ops::Sub;
 
-#[derive(PartialEq, Eq, Clone)]
-pub enum BST<T> {
-    /// A leaf is the bottom of the tree, it contains no data
-    Leaf,
-    /// A node contains a datum of type T, then left and right children.
-    /// Those children are in boxes, so the BST type has a fixed size.
-    Node(T, Box<BST<T>>, Box<BST<T>>),
-}
+#[path = "../../shared/p1_bst_common.rs"]
+mod common;
+pub use common::BST;
 
 impl<T: PartialOrd + Display> BST<T> {
     /// P1a: `len` computes the number of nodes in the BST `self`.
@@ -44,19 +40,24 @@ impl<T: PartialOrd + Display> BST<T> {
         }
     }
 
+    /// `is_empty` returns true if the BST `self` has no nodes.
+    pub fn is_empty(&self) -> bool {
+        matches!(self, BST::Leaf)
+    }
+
     /// P1b: `insert` takes a value of type T, and inserts it into the BST.
     /// `insert` must maintain the sorted invariant of the BST.
     ///
     /// This method should *NOT* be fancy, i.e. involve rotating or rebalancing
     /// the tree. The reference solution is 7 lines long.
     pub fn insert(&mut self, t: T) {
-        match self {
-            BST::Leaf => *self = BST::Node(t, Box::new(BST::Leaf), Box::new(BST::Leaf)),
-            BST::Node(t2, l, r) => {
-                let child = if t >= *t2 { r } else { l };
-                child.insert(t);
-            }
+        // Walk down with a loop rather than recursion, so inserting into a very
+        // deep (e.g. skewed) tree can't overflow the stack.
+        let mut node = self;
+        while let BST::Node(t2, l, r) = node {
+            node = if t >= *t2 { r } else { l };
         }
+        *node = BST::Node(t, Box::new(BST::Leaf), Box::new(BST::Leaf));
     }
 
     /// P1c: `search` takes a query of type &T, and returns the smallest element
@@ -74,6 +75,107 @@ impl<T: PartialOrd + Display> BST<T> {
         }
     }
 
+    /// `search_batch` computes `search` for every query, returning the results in the same
+    /// order as `queries`.
+    ///
+    /// Rather than descending from the root for each query, the queries are sorted and then
+    /// merged with a single in-order walk of the BST. This assumes that `T`'s ordering is total,
+    /// i.e. that any two queries can be compared, and panics otherwise (e.g. on a NaN).
+    pub fn search_batch(&self, queries: &[T]) -> Vec<Option<&T>> {
+        let mut order = (0..queries.len()).collect::<Vec<_>>();
+        order.sort_by(|&i, &j| {
+            queries[i]
+                .partial_cmp(&queries[j])
+                .expect("queries must be comparable")
+        });
+
+        let mut results = vec![None; queries.len()];
+        let mut elems = InOrder::new(self).peekable();
+        for i in order {
+            while elems.next_if(|s| **s < queries[i]).is_some() {}
+            results[i] = elems.peek().copied();
+        }
+        results
+    }
+
+    /// `fold` combines the elements of the BST in sorted order, starting from `init`,
+    /// like `Iterator::fold`. No intermediate vector of the elements is allocated.
+    pub fn fold<A, F: Fn(A, &T) -> A>(&self, init: A, f: F) -> A {
+        InOrder::new(self).fold(init, f)
+    }
+
+    /// `get` takes a query of type &T, and returns the element equal to the query element.
+    /// Unlike `search`, if no such element exists then it returns None rather than a larger element.
+    pub fn get(&self, query: &T) -> Option<&T> {
+        let mut node = self;
+        while let BST::Node(s, l, r) = node {
+            if query == s {
+                return Some(s);
+            }
+            node = if query > s { r } else { l };
+        }
+        None
+    }
+
+    /// `nearest` returns the element with the smallest absolute difference from `query`,
+    /// which may be smaller or larger than `query`. Ties are broken towards the smaller element.
+    pub fn nearest(&self, query: &T) -> Option<&T>
+    where
+        T: Copy + Sub<Output = T>,
+    {
+        self.nearest_by(query, |a, b| if a > b { *a - *b } else { *b - *a })
+    }
+
+    /// `nearest_by` is like `nearest`, but measures the distance between the query and each
+    /// element with `dist`. The distance must grow as elements get further from the query
+    /// in sorted order, so that the nearest element lies on the search path for the query.
+    pub fn nearest_by<D: PartialOrd>(&self, query: &T, dist: impl Fn(&T, &T) -> D) -> Option<&T> {
+        // The closest elements below and above the query are both visited on the way down,
+        // so the answer is the best of the elements on the path.
+        let mut best: Option<(&T, D)> = None;
+        let mut node = self;
+        while let BST::Node(s, l, r) = node {
+            let d = dist(query, s);
+            let closer = match &best {
+                None => true,
+                Some((b, bd)) => d < *bd || (d == *bd && s < *b),
+            };
+            if closer {
+                best = Some((s, d));
+            }
+            if query == s {
+                break;
+            }
+            node = if query > s { r } else { l };
+        }
+        best.map(|(s, _)| s)
+    }
+
+    /// `lower_bound` returns the number of elements strictly less than `key`, i.e. the index
+    /// of the first element not less than `key` in the sorted order of the BST.
+    ///
+    /// The BST doesn't store the sizes of its subtrees, so they are computed first, which
+    /// takes O(n) time. To answer many queries on the same tree, build a [`Ranks`] index
+    /// once with `ranks` instead.
+    pub fn lower_bound(&self, key: &T) -> usize {
+        self.ranks().lower_bound(key)
+    }
+
+    /// `upper_bound` returns the number of elements less than or equal to `key`, i.e. the index
+    /// of the first element greater than `key` in the sorted order of the BST. See `lower_bound`.
+    pub fn upper_bound(&self, key: &T) -> usize {
+        self.ranks().upper_bound(key)
+    }
+
+    /// `ranks` computes the size of every subtree of the BST in O(n) time, returning an index
+    /// that answers `lower_bound` and `upper_bound` queries in O(height) time.
+    pub fn ranks(&self) -> Ranks<'_, T> {
+        Ranks {
+            tree: self,
+            sizes: Sizes::of(self),
+        }
+    }
+
     /// P1d [CHALLENGE PROBLEM, try if you're feeling up to it!]
     ///
     /// `rebalance` performs a single rebalancing operation on the BST in-place (if applicable).
@@ -118,24 +220,45 @@ impl<T: PartialOrd + Display> BST<T> {
     ///
     /// You will also want to implement helper functions to perform the search for the new BST root.
     pub fn rebalance(&mut self) {
+        self.rebalance_step();
+    }
+
+    /// `rebalance_step` performs the same operation as `rebalance`, and returns whether
+    /// the BST was changed. A BST can be rebalanced at the root until it stops changing
+    /// with `while tree.rebalance_step() {}`.
+    pub fn rebalance_step(&mut self) -> bool {
         match self {
-            BST::Leaf => {}
+            BST::Leaf => false,
             BST::Node(_, l, r) => {
                 if l.len() > r.len() + 1 {
                     let s2 = l.right_spine().unwrap();
                     let l = mem::replace(l, Box::new(BST::Leaf));
                     let self_owned = mem::replace(self, BST::Leaf);
                     *self = BST::Node(s2, l, Box::new(self_owned));
+                    true
                 } else if r.len() > l.len() + 1 {
                     let s2 = r.left_spine().unwrap();
                     let r = mem::replace(r, Box::new(BST::Leaf));
                     let self_owned = mem::replace(self, BST::Leaf);
                     *self = BST::Node(s2, Box::new(self_owned), r);
+                    true
+                } else {
+                    false
                 }
             }
         }
     }
 
+    /// `pop_min` removes and returns the smallest element of the BST, if one exists.
+    pub fn pop_min(&mut self) -> Option<T> {
+        self.left_spine()
+    }
+
+    /// `pop_max` removes and returns the largest element of the BST, if one exists.
+    pub fn pop_max(&mut self) -> Option<T> {
+        self.right_spine()
+    }
+
     fn left_spine(&mut self) -> Option<T> {
         match self {
             BST::Leaf => None,
@@ -170,208 +293,847 @@ impl<T: PartialOrd + Display> BST<T> {
         }
     }
 
-    /// Provided helper function that gives a nice visual representation of a BST.
-    /// You can print any BST by doing `println!("{tree:?}")`.
+    /// `height` computes the number of nodes on the longest path from the root to a leaf.
+    pub fn height(&self) -> usize {
+        match self {
+            BST::Leaf => 0,
+            BST::Node(_, l, r) => 1 + l.height().max(r.height()),
+        }
+    }
+
+    /// `rebalance_full` rebuilds the BST so that its height is as small as possible.
+    pub fn rebalance_full(&mut self) {
+        let mut elems = Vec::new();
+        mem::replace(self, BST::Leaf).drain_sorted(&mut elems);
+        *self = BST::from_sorted(elems);
+    }
+
+    /// `extend_balanced` inserts every element of `iter` and then fully rebalances the BST,
+    /// so the result has logarithmic height regardless of the order of the elements.
+    pub fn extend_balanced<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for t in iter {
+            self.insert(t);
+        }
+        self.rebalance_full();
+    }
+
+    /// `from_iter_balanced` collects `iter` into a balanced BST in one shot, by sorting the
+    /// elements and building the tree from the middle outwards. If `dedup` is true, then
+    /// equal elements are only stored once.
     ///
-    /// Adapted from https://docs.rs/ascii_tree
-    fn fmt_levels(&self, f: &mut fmt::Formatter<'_>, level: Vec<usize>) -> fmt::Result {
-        use BST::*;
-        const EMPTY: &str = "   ";
-        const EDGE: &str = " └─";
-        const PIPE: &str = " │ ";
-        const BRANCH: &str = " ├─";
-
-        let maxpos = level.len();
-        let mut second_line = String::new();
-        for (pos, l) in level.iter().enumerate() {
-            let last_row = pos == maxpos - 1;
-            if *l == 1 {
-                if !last_row {
-                    write!(f, "{}", EMPTY)?
-                } else {
-                    write!(f, "{}", EDGE)?
-                }
-                second_line.push_str(EMPTY);
-            } else {
-                if !last_row {
-                    write!(f, "{}", PIPE)?
-                } else {
-                    write!(f, "{}", BRANCH)?
+    /// Unlike collecting via [`FromIterator`], which inserts one element at a time and can
+    /// produce a skewed tree, this takes O(n log n) time and yields O(log n) height.
+    pub fn from_iter_balanced<I: IntoIterator<Item = T>>(iter: I, dedup: bool) -> Self {
+        let mut elems = iter.into_iter().collect::<Vec<_>>();
+        elems.sort_by(|a, b| a.partial_cmp(b).expect("elements must be comparable"));
+        if dedup {
+            elems.dedup();
+        }
+        BST::from_sorted(elems)
+    }
+
+    /// `absorb` inserts every element of `other` into `self`, consuming `other`. Unlike
+    /// `extend_balanced` or `union`, the existing nodes of `self` are left in place, and
+    /// duplicates are kept.
+    ///
+    /// The elements are inserted in sorted order without rebalancing, so absorbing a large
+    /// tree can leave `self` unbalanced. Use `rebalance_full` afterwards if that matters.
+    pub fn absorb(&mut self, other: BST<T>) {
+        for t in Vec::from(other) {
+            self.insert(t);
+        }
+    }
+
+    /// `retain` removes every element for which `keep` returns false, like `Vec::retain`.
+    /// The remaining elements are rebuilt into a balanced BST.
+    pub fn retain<F: Fn(&T) -> bool>(&mut self, keep: F) {
+        let mut elems = Vec::new();
+        mem::replace(self, BST::Leaf).drain_sorted(&mut elems);
+        elems.retain(keep);
+        *self = BST::from_sorted(elems);
+    }
+
+    /// `split` divides the BST at `key`, returning a balanced BST of the elements less than
+    /// `key` and a balanced BST of the elements greater than or equal to `key`.
+    pub fn split(self, key: &T) -> (BST<T>, BST<T>) {
+        let mut elems = Vec::new();
+        self.drain_sorted(&mut elems);
+        let ge = elems.split_off(elems.partition_point(|t| t < key));
+        (BST::from_sorted(elems), BST::from_sorted(ge))
+    }
+
+    /// `union` returns a balanced BST of the elements in either `self` or `other`.
+    ///
+    /// Like the other set operations below, this treats both BSTs as sets: equal elements are
+    /// kept only once, preferring the one from `self`. The inputs are consumed so that elements
+    /// can be moved into the result rather than cloned.
+    pub fn union(self, other: Self) -> Self {
+        self.merge(other, true, true, true)
+    }
+
+    /// `intersection` returns a balanced BST of the elements in both `self` and `other`.
+    pub fn intersection(self, other: Self) -> Self {
+        self.merge(other, false, true, false)
+    }
+
+    /// `difference` returns a balanced BST of the elements in `self` but not in `other`.
+    pub fn difference(self, other: Self) -> Self {
+        self.merge(other, true, false, false)
+    }
+
+    /// `symmetric_difference` returns a balanced BST of the elements in exactly one of
+    /// `self` and `other`.
+    pub fn symmetric_difference(self, other: Self) -> Self {
+        self.merge(other, true, false, true)
+    }
+
+    /// Merges the in-order traversals of `self` and `other`, keeping the elements that are
+    /// only in `self`, in both, or only in `other` according to the flags.
+    fn merge(self, other: Self, left: bool, both: bool, right: bool) -> Self {
+        let sorted_set = |t: Self| {
+            let mut elems = Vec::from(t);
+            elems.dedup();
+            elems.into_iter().peekable()
+        };
+        let (mut xs, mut ys) = (sorted_set(self), sorted_set(other));
+        let mut elems = Vec::new();
+        loop {
+            match (xs.peek(), ys.peek()) {
+                (Some(x), Some(y)) if x < y => elems.extend(xs.next().filter(|_| left)),
+                (Some(x), Some(y)) if y < x => elems.extend(ys.next().filter(|_| right)),
+                (Some(_), Some(_)) => {
+                    ys.next();
+                    elems.extend(xs.next().filter(|_| both));
                 }
-                second_line.push_str(PIPE);
+                (Some(_), None) => elems.extend(xs.next().filter(|_| left)),
+                (None, Some(_)) => elems.extend(ys.next().filter(|_| right)),
+                (None, None) => break,
             }
         }
+        BST::from_sorted(elems)
+    }
+
+    /// `path_to` returns the sequence of elements visited while searching from the root
+    /// to the element equal to `query`, or None if no such element exists.
+    pub fn path_to(&self, query: &T) -> Option<Vec<&T>> {
+        let mut path = Vec::new();
+        let mut node = self;
+        while let BST::Node(s, l, r) = node {
+            path.push(s);
+            if query == s {
+                return Some(path);
+            }
+            node = if query < s { l } else { r };
+        }
+        None
+    }
 
+    /// Renders the BST in the Graphviz DOT format, e.g. to generate an image with
+    /// `dot -Tpng tree.dot -o tree.png`. Leaves are drawn as small points.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph BST {\n");
+        self.fmt_dot(&mut out, &mut 0);
+        out.push_str("}\n");
+        out
+    }
+
+    /// Writes the declarations and edges for the subtree `self` into `out`,
+    /// returning the id of the subtree's root.
+    fn fmt_dot(&self, out: &mut String, next_id: &mut usize) -> usize {
+        let id = *next_id;
+        *next_id += 1;
         match self {
-            Node(s, l, r) => {
-                let mut d = 2;
-                writeln!(f, " {s}")?;
-                for t in &[l, r] {
-                    let mut lnext = level.clone();
-                    lnext.push(d);
-                    d -= 1;
-                    t.fmt_levels(f, lnext)?;
+            BST::Leaf => out.push_str(&format!("  n{id} [shape=point];\n")),
+            BST::Node(s, l, r) => {
+                let label = s.to_string().replace('"', "\\\"");
+                out.push_str(&format!("  n{id} [label=\"{label}\"];\n"));
+                for child in [l, r] {
+                    let child_id = child.fmt_dot(out, next_id);
+                    out.push_str(&format!("  n{id} -> n{child_id};\n"));
                 }
             }
-            Leaf => writeln!(f)?,
         }
-        Ok(())
+        id
+    }
+}
+
+/// An iterator over references to the elements of a BST in sorted order.
+struct InOrder<'a, T> {
+    // The nodes whose element and right subtree have yet to be visited, deepest last.
+    stack: Vec<&'a BST<T>>,
+}
+
+impl<'a, T> InOrder<'a, T> {
+    fn new(t: &'a BST<T>) -> Self {
+        let mut iter = InOrder { stack: Vec::new() };
+        iter.push_left_spine(t);
+        iter
+    }
+
+    fn push_left_spine(&mut self, mut t: &'a BST<T>) {
+        while let BST::Node(_, l, _) = t {
+            self.stack.push(t);
+            t = l;
+        }
+    }
+}
+
+impl<'a, T> Iterator for InOrder<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        match self.stack.pop()? {
+            BST::Node(s, _, r) => {
+                self.push_left_spine(r);
+                Some(s)
+            }
+            BST::Leaf => unreachable!(),
+        }
+    }
+}
+
+/// The sizes of the subtrees of a BST, in a tree of the same shape.
+enum Sizes {
+    Leaf,
+    Node(usize, Box<Sizes>, Box<Sizes>),
+}
+
+impl Sizes {
+    fn of<T>(t: &BST<T>) -> Sizes {
+        match t {
+            BST::Leaf => Sizes::Leaf,
+            BST::Node(_, l, r) => {
+                let (l, r) = (Sizes::of(l), Sizes::of(r));
+                Sizes::Node(l.len() + 1 + r.len(), Box::new(l), Box::new(r))
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Sizes::Leaf => 0,
+            Sizes::Node(n, _, _) => *n,
+        }
+    }
+}
+
+/// An index over a BST that stores the size of each of its subtrees, so the rank of
+/// a key can be found by walking a single path down the tree. See [`BST::ranks`].
+pub struct Ranks<'a, T> {
+    tree: &'a BST<T>,
+    sizes: Sizes,
+}
+
+impl<T: PartialOrd> Ranks<'_, T> {
+    /// Returns the number of elements strictly less than `key`, like [`BST::lower_bound`].
+    pub fn lower_bound(&self, key: &T) -> usize {
+        self.rank(|s| s < key)
+    }
+
+    /// Returns the number of elements less than or equal to `key`, like [`BST::upper_bound`].
+    pub fn upper_bound(&self, key: &T) -> usize {
+        self.rank(|s| s <= key)
+    }
+
+    /// Counts the elements for which `before` is true, assuming that they come before all
+    /// of the other elements in sorted order.
+    fn rank(&self, before: impl Fn(&T) -> bool) -> usize {
+        let mut count = 0;
+        let (mut node, mut sizes) = (self.tree, &self.sizes);
+        while let (BST::Node(s, l, r), Sizes::Node(_, l_sizes, r_sizes)) = (node, sizes) {
+            if before(s) {
+                count += l_sizes.len() + 1;
+                (node, sizes) = (r, r_sizes);
+            } else {
+                (node, sizes) = (l, l_sizes);
+            }
+        }
+        count
+    }
+}
+
+// These helpers don't compare elements, so they're available for any `T`.
+impl<T> BST<T> {
+    /// Moves the elements of the BST into `out` in sorted order.
+    fn drain_sorted(self, out: &mut Vec<T>) {
+        if let BST::Node(s, l, r) = self {
+            l.drain_sorted(out);
+            out.push(s);
+            r.drain_sorted(out);
+        }
+    }
+}
+
+impl<T: PartialOrd> BST<T> {
+    /// Builds a balanced BST from a sorted vector, without cloning any elements.
+    ///
+    /// Each root is the first of its run of equal elements, so duplicates of it all go
+    /// in the right subtree as they would with [`BST::insert`].
+    fn from_sorted(mut elems: Vec<T>) -> BST<T> {
+        if elems.is_empty() {
+            return BST::Leaf;
+        }
+        let mid = elems.partition_point(|t| t < &elems[elems.len() / 2]);
+        let right = elems.split_off(mid + 1);
+        let s = elems.pop().unwrap();
+        BST::Node(
+            s,
+            Box::new(BST::from_sorted(elems)),
+            Box::new(BST::from_sorted(right)),
+        )
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<T: PartialOrd + Clone + Send + Sync> BST<T> {
+    /// Below this many elements, subtrees are built serially since the overhead
+    /// of spawning a task outweighs the work.
+    const PARALLEL_THRESHOLD: usize = 1024;
+
+    /// Builds a balanced BST from a sorted slice, building the left and right subtrees of
+    /// large slices in parallel. The result is the same tree as `BST::from(elems.to_vec())`.
+    pub fn from_sorted_slice_parallel(elems: &[T]) -> BST<T> {
+        if elems.len() < Self::PARALLEL_THRESHOLD {
+            return BST::from_sorted(elems.to_vec());
+        }
+        let mid = elems.partition_point(|t| t < &elems[elems.len() / 2]);
+        let (l, r) = rayon::join(
+            || BST::from_sorted_slice_parallel(&elems[..mid]),
+            || BST::from_sorted_slice_parallel(&elems[mid + 1..]),
+        );
+        BST::Node(elems[mid].clone(), Box::new(l), Box::new(r))
+    }
+}
+
+/// Builds a balanced BST from the elements of a vector, in any order.
+impl<T: PartialOrd> From<Vec<T>> for BST<T> {
+    fn from(mut elems: Vec<T>) -> Self {
+        elems.sort_by(|a, b| a.partial_cmp(b).expect("elements must be comparable"));
+        BST::from_sorted(elems)
+    }
+}
+
+/// Collects elements into a BST by inserting them one at a time, in order.
+///
+/// No rebalancing is performed, so collecting sorted elements yields a skewed tree.
+/// See [`BST::from_iter_balanced`] for a balanced alternative.
+impl<T: PartialOrd + Display> FromIterator<T> for BST<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut t = BST::Leaf;
+        for s in iter {
+            t.insert(s);
+        }
+        t
+    }
+}
+
+/// Drains a BST into a vector of its elements in sorted order.
+impl<T> From<BST<T>> for Vec<T> {
+    fn from(t: BST<T>) -> Self {
+        let mut elems = Vec::new();
+        t.drain_sorted(&mut elems);
+        elems
+    }
+}
+
+/// An element of a multiset, i.e. a value with the number of times it occurs.
+///
+/// Counted elements are compared only by their values, so a `BST<Counted<T>>`
+/// stores one node per distinct value.
+#[derive(Clone, Debug)]
+pub struct Counted<T> {
+    pub value: T,
+    pub count: usize,
+}
+
+impl<T: PartialEq> PartialEq for Counted<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
     }
 }
 
-impl<T: Debug + Display + PartialOrd> fmt::Debug for BST<T> {
+impl<T: PartialOrd> PartialOrd for Counted<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl<T: Display> Display for Counted<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.fmt_levels(f, vec![])
+        write!(f, "{} (x{})", self.value, self.count)
+    }
+}
+
+impl<T: PartialOrd + Display> BST<Counted<T>> {
+    /// `insert_count` inserts a value into a multiset BST. If the value is already
+    /// in the BST, then its count is incremented rather than adding a new node.
+    pub fn insert_count(&mut self, t: T) {
+        match self {
+            BST::Leaf => {
+                let c = Counted { value: t, count: 1 };
+                *self = BST::Node(c, Box::new(BST::Leaf), Box::new(BST::Leaf));
+            }
+            BST::Node(c, l, r) => {
+                if t == c.value {
+                    c.count += 1;
+                } else {
+                    let child = if t > c.value { r } else { l };
+                    child.insert_count(t);
+                }
+            }
+        }
+    }
+
+    /// `count` returns the number of times `query` was inserted into a multiset BST.
+    pub fn count(&self, query: &T) -> usize {
+        match self {
+            BST::Leaf => 0,
+            BST::Node(c, l, r) => {
+                if *query == c.value {
+                    c.count
+                } else if *query > c.value {
+                    r.count(query)
+                } else {
+                    l.count(query)
+                }
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
+    use super::common::TEST_TREE;
     use super::*;
-    use lazy_static::lazy_static;
     use BST::*;
 
-    lazy_static! {
-        static ref TEST_TREE: BST<&'static str> = {
-            Node(
-                "B",
-                Box::new(Node("A", Box::new(Leaf), Box::new(Leaf))),
-                Box::new(Node("C", Box::new(Leaf), Box::new(Leaf))),
-            )
-        };
+    #[test]
+    fn is_empty_test() {
+        let mut t = BST::<i32>::default();
+        assert!(t.is_empty());
+        t.insert(1);
+        assert!(!t.is_empty());
+        assert!(!TEST_TREE.is_empty());
     }
 
     #[test]
-    fn len_test() {
-        let heap_tree: Box<BST<i32>> = Box::new(BST::Leaf);
-        match &*heap_tree {
-            BST::Leaf => {}
-            BST::Node(t, _, _) => {
-                println!("{}", t);
-            }
-        };
+    fn insert_count_test() {
+        let mut t = BST::default();
+        for _ in 0..3 {
+            t.insert_count("A");
+        }
+        assert_eq!(t.count(&"A"), 3);
+        assert_eq!(t.len(), 1);
+
+        t.insert_count("B");
+        assert_eq!(t.count(&"B"), 1);
+        assert_eq!(t.count(&"C"), 0);
+        assert_eq!(t.len(), 2);
+    }
+
+    #[test]
+    fn insert_skewed_test() {
+        // Ascending insertions build a single right spine, which is as deep as possible.
+        // Inserting all N elements one at a time takes quadratic time, so build the spine
+        // for the first N directly, then insert the rest at its bottom.
+        const N: i32 = 200_000;
+        const INSERTS: i32 = 100;
+        let mut t = BST::Leaf;
+        for i in (0..N).rev() {
+            t = Node(i, Box::new(Leaf), Box::new(t));
+        }
+        for i in N..N + INSERTS {
+            t.insert(i);
+        }
 
-        assert_eq!(TEST_TREE.len(), 3);
+        // Dropping (or calling `len` on) the tree would recurse once per level,
+        // so take the spine apart iteratively.
+        let mut n = 0;
+        while let Node(s, l, r) = t {
+            assert_eq!(s, n);
+            assert!(matches!(*l, Leaf));
+            n += 1;
+            t = *r;
+        }
+        assert_eq!(n, N + INSERTS);
     }
 
     #[test]
-    fn insertion_test() {
+    fn get_test() {
         let mut t = TEST_TREE.clone();
         t.insert("E");
-        assert_eq!(
-            t,
-            Node(
-                "B",
-                Box::new(Node("A", Box::new(Leaf), Box::new(Leaf))),
-                Box::new(Node(
-                    "C",
-                    Box::new(Leaf),
-                    Box::new(Node("E", Box::new(Leaf), Box::new(Leaf)))
-                )),
-            )
-        );
+        assert_eq!(t.get(&"D"), None);
+        assert_eq!(t.search(&"D"), Some(&"E"));
+        assert_eq!(t.get(&"E"), Some(&"E"));
+        assert_eq!(t.get(&"A"), Some(&"A"));
+        assert_eq!(BST::Leaf.get(&"A"), None);
     }
 
     #[test]
-    fn search_test() {
+    fn path_to_test() {
         let mut t = TEST_TREE.clone();
         t.insert("E");
-        assert!(t.search(&"D") == Some(&"E"));
-        assert!(t.search(&"C") == Some(&"C"));
-        assert!(t.search(&"F") == None);
+        assert_eq!(t.path_to(&"A"), Some(vec![&"B", &"A"]));
+        assert_eq!(t.path_to(&"B"), Some(vec![&"B"]));
+        assert_eq!(t.path_to(&"E"), Some(vec![&"B", &"C", &"E"]));
+        assert_eq!(t.path_to(&"D"), None);
     }
 
     #[test]
-    fn rebalance1_test() {
-        let mut t = Node(
-            "D",
-            Box::new(Node(
-                "B",
-                Box::new(Node("A", Box::new(Leaf), Box::new(Leaf))),
-                Box::new(Node("C", Box::new(Leaf), Box::new(Leaf))),
-            )),
-            Box::new(Node("E", Box::new(Leaf), Box::new(Leaf))),
-        );
+    fn extend_balanced_test() {
+        let mut t = BST::default();
+        t.extend_balanced(1..=31);
+        assert_eq!(t.len(), 31);
+        assert!(t.height() <= 5);
+        assert_eq!(t.search(&17), Some(&17));
 
-        let t2 = Node(
-            "C",
-            Box::new(Node(
-                "B",
-                Box::new(Node("A", Box::new(Leaf), Box::new(Leaf))),
-                Box::new(Leaf),
-            )),
-            Box::new(Node(
-                "D",
-                Box::new(Leaf),
-                Box::new(Node("E", Box::new(Leaf), Box::new(Leaf))),
-            )),
-        );
+        let mut t2 = BST::Leaf;
+        for i in 1..=31 {
+            t2.insert(i);
+        }
+        assert_eq!(t2.height(), 31);
 
-        t.rebalance();
-        assert_eq!(t, t2);
+        let mut dups = BST::from(vec![2, 2]);
+        dups.extend_balanced([1, 2, 2, 3]);
+        assert!(is_valid(&dups, None, None));
+        assert_eq!(Vec::from(dups), vec![1, 2, 2, 2, 2, 3]);
+    }
+
+    /// Checks the BST invariant: every element is at least its left subtree and less than or
+    /// equal to its right subtree, where duplicates are inserted to the right.
+    fn is_valid<T: PartialOrd>(t: &BST<T>, lo: Option<&T>, hi: Option<&T>) -> bool {
+        match t {
+            Leaf => true,
+            Node(s, l, r) => {
+                lo.is_none_or(|lo| s >= lo)
+                    && hi.is_none_or(|hi| s < hi)
+                    && is_valid(l, lo, Some(s))
+                    && is_valid(r, Some(s), hi)
+            }
+        }
     }
 
     #[test]
-    fn rebalance2_test() {
-        let mut t = Node(
-            "A",
-            Box::new(Leaf),
-            Box::new(Node(
-                "B",
-                Box::new(Leaf),
-                Box::new(Node(
-                    "C",
-                    Box::new(Leaf),
-                    Box::new(Node("D", Box::new(Leaf), Box::new(Leaf))),
-                )),
-            )),
-        );
+    fn absorb_test() {
+        let mut t = BST::from(vec![2, 4, 6]);
+        t.absorb(BST::from(vec![1, 4, 7]));
+        assert_eq!(t.len(), 6);
+        assert!(is_valid(&t, None, None));
+        assert_eq!(Vec::from(t.clone()), vec![1, 2, 4, 4, 6, 7]);
 
-        let t2 = Node(
-            "B",
-            Box::new(Node("A", Box::new(Leaf), Box::new(Leaf))),
-            Box::new(Node(
-                "C",
-                Box::new(Leaf),
-                Box::new(Node("D", Box::new(Leaf), Box::new(Leaf))),
-            )),
-        );
+        // The root of `self` stays the root
+        assert!(matches!(t, BST::Node(4, _, _)));
+
+        let mut empty = BST::Leaf;
+        empty.absorb(TEST_TREE.clone());
+        assert_eq!(empty.len(), 3);
+        assert!(is_valid(&empty, None, None));
+    }
+
+    #[test]
+    fn split_test() {
+        let t = BST::from_iter(1..=10);
+        let (lt, ge) = t.split(&5);
+        assert_eq!(lt.len(), 4);
+        assert_eq!(ge.len(), 6);
+        assert!(is_valid(&lt, None, Some(&5)));
+        assert!(is_valid(&ge, Some(&5), None));
+        assert!(lt.height() <= 3 && ge.height() <= 3);
+        assert_eq!(Vec::from(lt), vec![1, 2, 3, 4]);
+        assert_eq!(Vec::from(ge), vec![5, 6, 7, 8, 9, 10]);
+
+        let (lt, ge) = BST::from(vec![1, 2, 3]).split(&0);
+        assert!(lt.is_empty());
+        assert_eq!(ge.len(), 3);
 
-        t.rebalance();
-        assert_eq!(t, t2);
+        let (lt, ge) = BST::from(vec![1, 1, 2, 2, 2, 3, 3]).split(&2);
+        assert!(is_valid(&lt, None, Some(&2)));
+        assert!(is_valid(&ge, Some(&2), None));
+        assert_eq!(Vec::from(ge), vec![2, 2, 2, 3, 3]);
     }
 
     #[test]
-    fn rebalance3_test() {
-        let mut t = Node(
-            "E",
-            Box::new(Node(
-                "B",
-                Box::new(Leaf),
-                Box::new(Node(
-                    "D",
-                    Box::new(Node("C", Box::new(Leaf), Box::new(Leaf))),
-                    Box::new(Leaf),
-                )),
-            )),
-            Box::new(Node("F", Box::new(Leaf), Box::new(Leaf))),
+    fn retain_test() {
+        let mut t = BST::default();
+        for i in [5, 2, 8, 1, 9, 3, 7, 10, 4, 6] {
+            t.insert(i);
+        }
+        t.retain(|n| n % 2 == 0);
+        assert_eq!(t.len(), 5);
+        assert!(is_valid(&t, None, None));
+        assert!(t.height() <= 3);
+        for i in 1..=10 {
+            assert_eq!(t.get(&i).is_some(), i % 2 == 0, "i = {i}");
+        }
+
+        t.retain(|_| false);
+        assert!(t.is_empty());
+
+        let mut dups = BST::from(vec![1, 1, 2, 2, 3, 3, 4, 4]);
+        dups.retain(|&n| n != 3);
+        assert!(is_valid(&dups, None, None));
+        assert_eq!(Vec::from(dups), vec![1, 1, 2, 2, 4, 4]);
+    }
+
+    #[test]
+    fn from_vec_test() {
+        let sorted = (1..=20).collect::<Vec<_>>();
+        let t = BST::from(sorted.clone());
+        assert!(is_valid(&t, None, None));
+        assert!(t.height() <= 5);
+        assert_eq!(Vec::from(t), sorted);
+
+        let t: BST<_> = vec!["C", "A", "D", "B"].into();
+        assert_eq!(t.len(), 4);
+        assert_eq!(Vec::from(t), vec!["A", "B", "C", "D"]);
+
+        let empty: Vec<i32> = BST::Leaf.into();
+        assert!(empty.is_empty());
+
+        // Duplicates of a root must all be in its right subtree
+        let t = BST::from(vec![1, 1]);
+        assert!(is_valid(&t, None, None));
+        assert!(matches!(&t, BST::Node(1, l, _) if l.is_empty()));
+        for dups in [vec![1, 1, 1, 1, 1], vec![3, 1, 2, 2, 2, 2, 3, 1, 2, 0]] {
+            let t = BST::from(dups.clone());
+            assert!(is_valid(&t, None, None), "{dups:?}");
+            let twos = dups.iter().filter(|&&n| n == 2).count();
+            assert_eq!(t.upper_bound(&2) - t.lower_bound(&2), twos);
+        }
+    }
+
+    #[test]
+    fn from_iter_balanced_test() {
+        let t = BST::from_iter_balanced(1..=1000, false);
+        assert_eq!(t.len(), 1000);
+        assert!(t.height() <= 11);
+        assert!(is_valid(&t, None, None));
+
+        let naive = (1..=1000).collect::<BST<_>>();
+        assert_eq!(naive.len(), 1000);
+        assert_eq!(naive.height(), 1000);
+
+        let t = BST::from_iter_balanced([3, 1, 2, 3, 1], true);
+        assert_eq!(Vec::from(t), vec![1, 2, 3]);
+        let t = BST::from_iter_balanced([3, 1, 2, 3, 1], false);
+        assert!(is_valid(&t, None, None));
+        assert_eq!(Vec::from(t), vec![1, 1, 2, 3, 3]);
+    }
+
+    #[test]
+    fn set_operations_test() {
+        let evens = || BST::from((0..20).step_by(2).collect::<Vec<_>>());
+        let threes = || BST::from((0..20).step_by(3).collect::<Vec<_>>());
+
+        let union = evens().union(threes());
+        assert!(is_valid(&union, None, None));
+        assert!(union.height() <= 4);
+        assert_eq!(
+            Vec::from(union),
+            vec![0, 2, 3, 4, 6, 8, 9, 10, 12, 14, 15, 16, 18]
         );
 
-        let t2 = Node(
-            "D",
-            Box::new(Node(
-                "B",
-                Box::new(Leaf),
-                Box::new(Node("C", Box::new(Leaf), Box::new(Leaf))),
-            )),
-            Box::new(Node(
-                "E",
-                Box::new(Leaf),
-                Box::new(Node("F", Box::new(Leaf), Box::new(Leaf))),
-            )),
+        let intersection = evens().intersection(threes());
+        assert!(is_valid(&intersection, None, None));
+        assert_eq!(Vec::from(intersection), vec![0, 6, 12, 18]);
+
+        let difference = evens().difference(threes());
+        assert!(is_valid(&difference, None, None));
+        assert_eq!(Vec::from(difference), vec![2, 4, 8, 10, 14, 16]);
+        assert_eq!(Vec::from(threes().difference(evens())), vec![3, 9, 15]);
+
+        let symmetric = evens().symmetric_difference(threes());
+        assert_eq!(Vec::from(symmetric), vec![2, 3, 4, 8, 9, 10, 14, 15, 16]);
+
+        // Duplicates within a tree are treated as a single element
+        let dups = BST::from(vec![1, 1, 2]);
+        assert!(is_valid(&dups, None, None));
+        let union = dups.clone().union(BST::Leaf);
+        assert!(is_valid(&union, None, None));
+        assert_eq!(Vec::from(union), vec![1, 2]);
+        assert_eq!(Vec::from(dups.difference(BST::from(vec![1]))), vec![2]);
+        assert!(evens().intersection(BST::Leaf).is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn from_sorted_slice_parallel_test() {
+        let sorted = (0..10_000).collect::<Vec<_>>();
+        let t = BST::from_sorted_slice_parallel(&sorted);
+        assert_eq!(t, BST::from(sorted.clone()));
+        assert!(is_valid(&t, None, None));
+
+        let small = BST::from_sorted_slice_parallel(&sorted[..10]);
+        assert_eq!(small, BST::from(sorted[..10].to_vec()));
+
+        let dups = (0..5000).map(|i| i / 7).collect::<Vec<_>>();
+        let t = BST::from_sorted_slice_parallel(&dups);
+        assert_eq!(t, BST::from(dups.clone()));
+        assert!(is_valid(&t, None, None));
+        assert!(BST::<i32>::from_sorted_slice_parallel(&[]).is_empty());
+    }
+
+    #[test]
+    fn rebalance_step_test() {
+        let mut t = TEST_TREE.clone();
+        assert!(!t.rebalance_step());
+        assert_eq!(t, *TEST_TREE);
+
+        let mut t = BST::Leaf;
+        for s in ["A", "B", "C"] {
+            t.insert(s);
+        }
+        assert!(t.rebalance_step());
+        assert_eq!(t, *TEST_TREE);
+        assert!(!t.rebalance_step());
+
+        let mut t = BST::Leaf;
+        for i in 1..=7 {
+            t.insert(i);
+        }
+        let mut steps = 0;
+        while t.rebalance_step() {
+            steps += 1;
+        }
+        assert!(steps > 0);
+        assert_eq!(t.len(), 7);
+    }
+
+    #[test]
+    fn search_batch_test() {
+        // A simple LCG, so the test is deterministic
+        let mut state: u64 = 0x853c_49e6_748f_ea9b;
+        let mut next = || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (state >> 33) % 1000
+        };
+
+        let mut t = BST::Leaf;
+        for _ in 0..200 {
+            t.insert(next());
+        }
+        let queries = (0..300).map(|_| next() + 10).collect::<Vec<_>>();
+        let expected = queries.iter().map(|q| t.search(q)).collect::<Vec<_>>();
+        assert_eq!(t.search_batch(&queries), expected);
+        assert!(expected.contains(&None));
+
+        assert_eq!(
+            TEST_TREE.search_batch(&["C", "0", "B", "D"]),
+            vec![Some(&"C"), Some(&"A"), Some(&"B"), None]
         );
+        assert!(TEST_TREE.search_batch(&[]).is_empty());
+    }
+
+    #[test]
+    fn fold_test() {
+        let t = BST::from(vec![5, -3, 12, 7, 0, 7, 42]);
+        let sum = t.fold(0, |acc, s| acc + s);
+        assert_eq!(sum, Vec::from(t.clone()).iter().sum::<i32>());
+
+        // Elements are visited in sorted order
+        let joined = t.fold(String::new(), |acc, s| format!("{acc}{s},"));
+        assert_eq!(joined, "-3,0,5,7,7,12,42,");
+        assert_eq!(BST::<i32>::Leaf.fold(1, |acc, s| acc * s), 1);
+    }
+
+    #[test]
+    fn nearest_test() {
+        let t = BST::from(vec![1, 10, 20, 30]);
+        // 10 is below the query and closer than the ceiling 20
+        assert_eq!(t.nearest(&12), Some(&10));
+        assert_eq!(t.search(&12), Some(&20));
+        assert_eq!(t.nearest(&18), Some(&20));
+        assert_eq!(t.nearest(&15), Some(&10));
+        assert_eq!(t.nearest(&30), Some(&30));
+        assert_eq!(t.nearest(&100), Some(&30));
+        assert_eq!(t.nearest(&-5), Some(&1));
+        assert_eq!(BST::<i32>::Leaf.nearest(&0), None);
+
+        // Skewed trees visit every element on the path
+        let t = BST::from_iter([1, 2, 3, 4, 5]);
+        assert_eq!(t.nearest(&4), Some(&4));
+        let t = BST::from_iter([5.0f64, 4.0, 3.0, 2.0, 1.0]);
+        assert_eq!(t.nearest(&2.4), Some(&2.0));
+        // 2.0 and 3.0 are both at distance 0 after rounding, so the smaller one wins
+        assert_eq!(t.nearest_by(&2.6, |a, b| (a - b).abs().floor()), Some(&2.0));
+    }
+
+    #[test]
+    fn bounds_test() {
+        let t = BST::from_iter_balanced(1..=10, false);
+        assert_eq!(t.lower_bound(&5), 4);
+        assert_eq!(t.upper_bound(&5), 5);
+        assert_eq!(t.lower_bound(&0), 0);
+        assert_eq!(t.upper_bound(&10), 10);
+        assert_eq!(t.lower_bound(&11), 10);
+
+        // Duplicates are all counted by `upper_bound`, but none by `lower_bound`
+        let t = BST::from_iter([3, 7, 5, 5, 1, 5, 9]);
+        assert_eq!(t.lower_bound(&5), 2);
+        assert_eq!(t.upper_bound(&5), 5);
+        assert_eq!(t.lower_bound(&6), 5);
+        assert_eq!(BST::<i32>::Leaf.upper_bound(&0), 0);
+
+        // An index answers many queries, agreeing with counting the sorted elements
+        let t = BST::from_iter([8, 3, 10, 1, 6, 14, 4, 7, 13, 6]);
+        let sorted = Vec::from(t.clone());
+        let ranks = t.ranks();
+        for key in 0..=15 {
+            let less = sorted.iter().filter(|s| **s < key).count();
+            let less_eq = sorted.iter().filter(|s| **s <= key).count();
+            assert_eq!(ranks.lower_bound(&key), less, "key = {key}");
+            assert_eq!(ranks.upper_bound(&key), less_eq, "key = {key}");
+        }
+    }
+
+    #[test]
+    fn hash_test() {
+        use std::collections::HashSet;
+        let mut set = HashSet::new();
+        set.insert(BST::from_iter([2, 1, 3]));
+        set.insert(BST::from_iter([2, 3, 1]));
+        assert_eq!(set.len(), 1);
+
+        // Same contents, different shape
+        set.insert(BST::from_iter([1, 2, 3]));
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&BST::from_iter([1, 2, 3])));
+    }
 
-        t.rebalance();
-        assert_eq!(t, t2);
+    #[test]
+    fn to_dot_test() {
+        let dot = TEST_TREE.to_dot();
+        assert!(dot.starts_with("digraph BST {"));
+        assert_eq!(dot.matches("[label=").count(), 3);
+        assert_eq!(dot.matches("[shape=point]").count(), 4);
+        assert_eq!(dot.matches("->").count(), 6);
+        assert!(dot.contains("[label=\"B\"]"));
+    }
+
+    #[test]
+    fn pop_min_test() {
+        let mut t = BST::default();
+        for s in ["C", "A", "E", "B", "D"] {
+            t.insert(s);
+        }
+
+        let mut popped = vec![];
+        while let Some(s) = t.pop_min() {
+            popped.push(s);
+        }
+        assert_eq!(popped, vec!["A", "B", "C", "D", "E"]);
+        assert!(t.is_empty());
+    }
+
+    #[test]
+    fn pop_max_test() {
+        let mut t = TEST_TREE.clone();
+        t.insert("E");
+        assert_eq!(t.pop_max(), Some("E"));
+        assert_eq!(t.pop_max(), Some("C"));
+        assert_eq!(t.len(), 2);
+        assert_eq!(t.pop_max(), Some("B"));
+        assert_eq!(t.pop_max(), Some("A"));
+        assert_eq!(t.pop_max(), None);
     }
 }
@@ -3,7 +3,7 @@
 //!
 //! # P2: Optimizing image algorithms
 //!
-//! This file provides an `Image` data structure for representing grayscale 2D images.
+//! This file provides an `Image` data structure for representing 2D images, which are grayscale by default.
 //! This file also provides an implementation of a seam carving algorithm. Seam carving
 //! is a form of content-aware resizing, i.e. it lets you make an image smaller without
 //! distorting or cropping important content. I would recommend reading the "Process" section
@@ -96,50 +96,193 @@
 //! BENCH=castle cargo bench
 //! ```
 
-use image::EncodableLayout;
-use std::{collections::HashMap, path::Path};
+use image::{ColorType, DynamicImage};
+use std::{collections::HashMap, fmt, mem, path::Path};
 
+/// A type of pixel that can be stored in an [`Image`] and carved.
+pub trait Pixel: Copy + Default {
+    /// The number of bytes used to encode one pixel.
+    const BYTES: usize;
+
+    /// The color type used when saving an image of these pixels.
+    const COLOR_TYPE: ColorType;
+
+    /// The difference between two pixels, used to compute energies.
+    fn energy_diff(&self, other: &Self) -> usize;
+
+    /// The brightness of the pixel, from 0 to 255.
+    fn intensity(&self) -> f64;
+
+    /// Converts a decoded image into a flat buffer of `BYTES`-sized pixels.
+    fn decode(img: &DynamicImage) -> Vec<u8>;
+
+    /// Reads a pixel from a slice of exactly `BYTES` bytes.
+    fn from_bytes(bytes: &[u8]) -> Self;
+
+    /// Appends the bytes of a pixel to `buf`.
+    fn write_bytes(&self, buf: &mut Vec<u8>);
+}
+
+/// An 8-bit grayscale pixel.
+pub type Luma8 = u8;
+
+/// An 8-bit RGB pixel.
+pub type Rgb8 = [u8; 3];
+
+impl Pixel for Luma8 {
+    const BYTES: usize = 1;
+    const COLOR_TYPE: ColorType = ColorType::L8;
+
+    fn energy_diff(&self, other: &Self) -> usize {
+        self.abs_diff(*other) as usize
+    }
+
+    fn intensity(&self) -> f64 {
+        *self as f64
+    }
+
+    fn decode(img: &DynamicImage) -> Vec<u8> {
+        image::imageops::colorops::grayscale(img).into_raw()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        bytes[0]
+    }
+
+    fn write_bytes(&self, buf: &mut Vec<u8>) {
+        buf.push(*self);
+    }
+}
+
+impl Pixel for Rgb8 {
+    const BYTES: usize = 3;
+    const COLOR_TYPE: ColorType = ColorType::Rgb8;
+
+    fn energy_diff(&self, other: &Self) -> usize {
+        // Sum the differences of each channel
+        self.iter()
+            .zip(other)
+            .map(|(a, b)| a.abs_diff(*b) as usize)
+            .sum()
+    }
+
+    fn intensity(&self) -> f64 {
+        self.iter().map(|c| *c as f64).sum::<f64>() / 3.
+    }
+
+    fn decode(img: &DynamicImage) -> Vec<u8> {
+        img.to_rgb8().into_raw()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        [bytes[0], bytes[1], bytes[2]]
+    }
+
+    fn write_bytes(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
+}
+
+/// An error from carving an image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CarveError {
+    /// The image has no pixels to carve.
+    Empty { width: usize, height: usize },
+}
+
+impl fmt::Display for CarveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CarveError::Empty { width, height } => {
+                write!(f, "cannot carve a {width}x{height} image")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CarveError {}
+
+/// A 2D image whose pixels have type `P`, grayscale by default.
 #[derive(Clone)]
-pub struct Image {
-    pixels: Vec<u8>,
+pub struct Image<P = Luma8> {
+    pixels: Vec<P>,
     width: usize,
     height: usize,
 }
 
+/// A grid of energies.
+///
+/// Energies are stored as `u64` rather than `usize`: propagating energies down the image sums
+/// one pixel energy per row, and on 32-bit targets a `usize` sum can overflow for tall images
+/// with large pixel differences. A `u64` can hold the sum for any image that fits in memory.
+#[derive(Clone)]
 pub struct Matrix {
-    data: Vec<usize>,
+    data: Vec<u64>,
     width: usize,
 }
 impl Matrix {
-    fn new(width: usize, height: usize) -> Self {
+    /// Creates a `width` by `height` grid with every energy set to 0.
+    pub fn new(width: usize, height: usize) -> Self {
         Matrix {
             data: vec![0; width * height],
             width,
         }
     }
 
-    fn get(&self, x: usize, y: usize) -> usize {
+    /// Returns the energy at `(x, y)`.
+    pub fn get(&self, x: usize, y: usize) -> u64 {
         self.data[x + y * self.width]
     }
 
-    fn set(&mut self, x: usize, y: usize, value: usize) {
+    /// Sets the energy at `(x, y)` to `value`.
+    pub fn set(&mut self, x: usize, y: usize, value: u64) {
         self.data[x + y * self.width] = value;
     }
 
-    fn row_slice(&self, x1: usize, x2: usize, y: usize) -> &[usize] {
+    fn row_slice(&self, x1: usize, x2: usize, y: usize) -> &[u64] {
         &self.data[(x1 + (y * self.width))..=(x2 + (y * self.width))]
     }
+
+    fn row(&self, y: usize) -> &[u64] {
+        &self.data[y * self.width..(y + 1) * self.width]
+    }
 }
 
 /// Data structure to hold energies.
 type Energies = Matrix;
 
-impl Image {
-    pub fn get(&self, x: usize, y: usize) -> u8 {
+impl<P: Pixel> Image<P> {
+    /// Creates an image from its pixels in row-major order, i.e. the pixel at `(x, y)`
+    /// is `pixels[x + y * width]`.
+    ///
+    /// Panics if there are not exactly `width * height` pixels.
+    pub fn from_raw(width: usize, height: usize, pixels: Vec<P>) -> Self {
+        assert_eq!(
+            pixels.len(),
+            width * height,
+            "a {width}x{height} image needs {} pixels",
+            width * height
+        );
+        Image {
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> P {
         self.pixels[x + y * self.width]
     }
 
-    pub fn set(&mut self, x: usize, y: usize, pixel: u8) {
+    pub fn set(&mut self, x: usize, y: usize, pixel: P) {
         self.pixels[x + y * self.width] = pixel;
     }
 
@@ -150,33 +293,75 @@ impl Image {
             .then_some((x2 as usize, y2 as usize))
     }
 
+    /// Computes the initial energy of a single pixel, i.e. the average difference
+    /// of the pixel versus its neighbors.
+    fn pixel_energy(&self, x: usize, y: usize) -> u64 {
+        let mut diff_sum = 0;
+        let mut diff_count = 0;
+        // Only compute self.get(x, y) once
+        let px = self.get(x, y);
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if let Some((x2, y2)) = self.offset(x, y, dx, dy) {
+                    // Don't accumulate a vector of differences
+                    diff_sum += px.energy_diff(&self.get(x2, y2)) as u64;
+                    diff_count += 1;
+                }
+            }
+        }
+        diff_sum / diff_count
+    }
 
     /// Generates the initial mapping from pixels to energy. The initial energy of a pixel
     /// is the average difference of the pixel versus its neighbors.
     pub fn compute_initial_energy(&self) -> Energies {
         // Use a vector representation of energy instead of a hashmap
         let mut energies = Matrix::new(self.width, self.height);
+        self.compute_initial_energy_into(&mut energies);
+        energies
+    }
+
+    /// Computes the initial energies like [`Image::compute_initial_energy`], but writes them
+    /// into `energies`, reusing its buffer. Doesn't allocate if the buffer is already large enough.
+    fn compute_initial_energy_into(&self, energies: &mut Energies) {
+        energies.data.resize(self.width * self.height, 0);
+        energies.width = self.width;
 
         for y in 0..self.height {
             for x in 0..self.width {
-                let mut diff_sum = 0;
-                let mut diff_count = 0;
-                // Only compute self.get(x, y) once
-                let px = self.get(x, y);
-                for dy in -1..=1 {
-                    for dx in -1..=1 {
-                        if let Some((x2, y2)) = self.offset(x, y, dx, dy) {
-                            // Don't accumulate a vector of differences
-                            diff_sum += px.abs_diff(self.get(x2, y2)) as usize;
-                            diff_count += 1;
-                        }
-                    }
-                }
-                energies.set(x, y, diff_sum / diff_count);
+                energies.set(x, y, self.pixel_energy(x, y));
             }
         }
+    }
 
-        energies
+    /// Updates the initial energies of the image that `seam` was removed from, producing
+    /// the initial energies of `self` (the image after removal).
+    ///
+    /// Removing a seam only changes the neighborhoods of pixels within a column of the seam,
+    /// so every other energy is copied from `energies` rather than recomputed.
+    pub fn update_initial_energy(&self, energies: &Energies, seam: &[usize]) -> Energies {
+        let mut updated = Matrix::new(self.width, self.height);
+
+        for y in 0..self.height {
+            let x_r = seam[y];
+            let src_row = energies.row(y);
+            let dst_row = &mut updated.data[y * self.width..(y + 1) * self.width];
+            dst_row[0..x_r].copy_from_slice(&src_row[0..x_r]);
+            dst_row[x_r..].copy_from_slice(&src_row[x_r + 1..]);
+            if self.width == 0 {
+                continue;
+            }
+
+            // Pixels outside [lo - 1, hi] keep the same neighbors after the removal
+            let rows = y.saturating_sub(1)..=(y + 1).min(self.height - 1);
+            let lo = rows.clone().map(|y2| seam[y2]).min().unwrap();
+            let hi = rows.map(|y2| seam[y2]).max().unwrap();
+            for x in lo.saturating_sub(1)..=hi.min(self.width - 1) {
+                updated.set(x, y, self.pixel_energy(x, y));
+            }
+        }
+
+        updated
     }
 
     /// Propagates minimal energies down the image.
@@ -202,86 +387,1495 @@ impl Image {
     /// Finds the lowest-energy seam by starting at the bottom, and following the
     /// smallest adjacent energy values.
     pub fn find_seam(&self, energies: &Energies) -> Vec<usize> {
+        let mut min_seam = Vec::with_capacity(self.height);
+        self.find_seam_into(energies, 0, &mut min_seam);
+        min_seam
+    }
+
+    /// Finds the lowest-cost seam given the *initial* energies `initial`, where every step
+    /// that moves left or right between rows adds `weight` to the cost. Higher weights produce
+    /// straighter seams, which reduces the zig-zag artifacts left behind after carving.
+    ///
+    /// The penalty is included while propagating the energies, so the seam is the cheapest
+    /// under the penalized cost rather than a straightened version of the unpenalized seam.
+    /// A weight of 0 finds the same seam as [`Image::find_seam`].
+    pub fn find_seam_straight(&self, initial: &Energies, weight: u64) -> Vec<usize> {
+        let mut energies = initial.clone();
+        self.propagate_energy_straight(&mut energies, weight);
+        let mut min_seam = Vec::with_capacity(self.height);
+        self.find_seam_into(&energies, weight, &mut min_seam);
+        min_seam
+    }
+
+    /// Propagates energies like [`Image::propagate_energy`], but adds `weight` to the
+    /// neighbors above-left and above-right. Sums saturate rather than overflow.
+    fn propagate_energy_straight(&self, energies: &mut Energies, weight: u64) {
+        for y in 1..self.height {
+            for x in 0..self.width {
+                let above = energies.get(x, y - 1);
+                let left = match x {
+                    0 => u64::MAX,
+                    _ => energies.get(x - 1, y - 1).saturating_add(weight),
+                };
+                let right = match x + 1 < self.width {
+                    true => energies.get(x + 1, y - 1).saturating_add(weight),
+                    false => u64::MAX,
+                };
+                let emin = above.min(left).min(right);
+                energies.set(x, y, energies.get(x, y).saturating_add(emin));
+            }
+        }
+    }
+
+    /// Finds a seam in the propagated `energies` like [`Image::find_seam`], but writes it
+    /// into `min_seam`, reusing its buffer. `weight` must be the move penalty the energies
+    /// were propagated with.
+    fn find_seam_into(&self, energies: &Energies, weight: u64, min_seam: &mut Vec<usize>) {
         // No significant changes, just using the alternative `energies` interface
         let (y_seed, _) = (0..self.width)
             .map(|x| (x, energies.get(x, self.height - 1)))
             .min_by_key(|(_, e)| *e)
             .unwrap();
-        let mut min_seam = vec![y_seed];
+        min_seam.clear();
+        min_seam.push(y_seed);
         for y in 0..(self.height - 1) {
             let (x, _) = (-1..=1)
-                .filter_map(|dx| self.offset(min_seam[y], self.height - y - 1, dx, -1))
-                .map(|(x2, y2)| (x2, energies.get(x2, y2)))
+                .filter_map(|dx| {
+                    let (x2, y2) = self.offset(min_seam[y], self.height - y - 1, dx, -1)?;
+                    let penalty = if dx == 0 { 0 } else { weight };
+                    Some((x2, energies.get(x2, y2).saturating_add(penalty)))
+                })
                 .min_by_key(|(_, e)| *e)
                 .unwrap();
             min_seam.push(x);
         }
-        min_seam
+    }
+
+    /// Finds the lowest-energy seam given the *initial* energies `initial`, except that the
+    /// seam will not pass through column `forbidden_x` unless it has no alternative.
+    ///
+    /// The column is avoided by adding a large penalty to its initial energies before
+    /// propagating them, so the result is the cheapest seam outside the column. The penalty
+    /// outweighs any real seam, yet a seam through every row of the column still can't overflow.
+    pub fn find_seam_avoiding(&self, initial: &Energies, forbidden_x: usize) -> Vec<usize> {
+        let penalty = u64::MAX / 2 / self.height.max(1) as u64;
+        let mut energies = initial.clone();
+        for y in 0..self.height {
+            let e = energies.get(forbidden_x, y);
+            energies.set(forbidden_x, y, e + penalty);
+        }
+        self.propagate_energy(&mut energies);
+        self.find_seam(&energies)
     }
 
     /// Takes a vertical seam as a vector [x_1, ... x_n] of x-values,
     /// and removes it from the image.
-    pub fn remove_seam(&self, seam: &[usize]) -> Image {
-        let mut pixels = vec![0; (self.width - 1) * self.height];
-        for y in 0..self.height {
-            let x_r = seam[y];
+    pub fn remove_seam(&self, seam: &[usize]) -> Self {
+        let mut dst = Image {
+            pixels: Vec::with_capacity((self.width - 1) * self.height),
+            width: 0,
+            height: 0,
+        };
+        self.remove_seam_into(seam, &mut dst);
+        dst
+    }
+
+    /// Removes a vertical seam like [`Image::remove_seam`], but writes the result into `dst`,
+    /// replacing its contents and reusing its buffer. Doesn't allocate if `dst` already has
+    /// room for the result.
+    fn remove_seam_into(&self, seam: &[usize], dst: &mut Self) {
+        dst.pixels.clear();
+        for (y, &x_r) in seam.iter().enumerate().take(self.height) {
             let src_row = &self.pixels[y * self.width..(y + 1) * self.width];
-            let dst_row = &mut pixels[y * (self.width - 1)..(y + 1) * (self.width - 1)];
-            dst_row[0..x_r].copy_from_slice(&src_row[0..x_r]);
-            dst_row[x_r..].copy_from_slice(&src_row[x_r + 1..]);
+            dst.pixels.extend_from_slice(&src_row[0..x_r]);
+            dst.pixels.extend_from_slice(&src_row[x_r + 1..]);
         }
+        dst.width = self.width - 1;
+        dst.height = self.height;
+    }
 
-        Image {
-            width: self.width - 1,
-            height: self.height,
-            pixels,
+    /// Removes a vertical seam like [`Image::remove_seam`], but shifts the remaining pixels
+    /// left within the existing buffer instead of allocating a new one.
+    pub fn remove_seam_in_place(&mut self, seam: &[usize]) {
+        let width = self.width;
+        // Each row moves left by the number of pixels removed from the rows above it,
+        // so its destination never overlaps a row that has yet to be moved.
+        for (y, &x_r) in seam.iter().enumerate().take(self.height) {
+            let (src, dst) = (y * width, y * (width - 1));
+            self.pixels.copy_within(src..src + x_r, dst);
+            self.pixels
+                .copy_within(src + x_r + 1..src + width, dst + x_r);
         }
+        self.pixels.truncate((width - 1) * self.height);
+        self.width = width - 1;
     }
 
     /// Carves out the single lowest-energy seam from an image.
     ///
     /// Returns a new image with the seam removed.
+    ///
+    /// Panics if the image has no pixels. See [`Image::try_carve`] for a version that returns an error instead.
     pub fn carve(&self) -> Self {
-        let mut energies = self.compute_initial_energy();
+        self.try_carve()
+            .expect("cannot carve an image with zero width or height")
+    }
+
+    /// Carves out the single lowest-energy seam from an image, or returns an error if
+    /// the image has no pixels (and hence no seams).
+    pub fn try_carve(&self) -> Result<Self, CarveError> {
+        self.try_carve_with(&Gradient)
+    }
+
+    /// Carves out the single lowest-energy seam from an image like [`Image::carve`], but with
+    /// the initial energy of each pixel computed by `energy`.
+    ///
+    /// Panics if the image has no pixels. See [`Image::try_carve_with`] for a version that returns an error instead.
+    pub fn carve_with(&self, energy: &impl EnergyFn<P>) -> Self {
+        self.try_carve_with(energy)
+            .expect("cannot carve an image with zero width or height")
+    }
+
+    /// Carves out a seam like [`Image::carve_with`], or returns an error if the image has
+    /// no pixels (and hence no seams).
+    ///
+    /// Panics if `energy` returns a grid that isn't the same size as the image.
+    pub fn try_carve_with(&self, energy: &impl EnergyFn<P>) -> Result<Self, CarveError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(CarveError::Empty {
+                width: self.width,
+                height: self.height,
+            });
+        }
+        let mut energies = energy.energies(self);
+        assert!(
+            energies.width == self.width && energies.data.len() == self.width * self.height,
+            "energies must be {}x{}",
+            self.width,
+            self.height
+        );
         self.propagate_energy(&mut energies);
         let min_seam = self.find_seam(&energies);
+        Ok(self.remove_seam(&min_seam))
+    }
+
+    /// Carves out the single lowest-energy seam from an image, storing energies in a
+    /// `HashMap` keyed by pixel coordinates as the starter code does.
+    ///
+    /// Produces the same image as [`Image::carve`], so the two can be benchmarked
+    /// to measure the cost of the `HashMap` versus a flat vector.
+    pub fn carve_hashmap(&self) -> Self {
+        let mut energies = HashMap::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                energies.insert((x, y), self.pixel_energy(x, y));
+            }
+        }
+
+        for y in 1..self.height {
+            for x in 0..self.width {
+                let emin = (-1..=1)
+                    .filter_map(|dx| self.offset(x, y, dx, -1))
+                    .map(|(x, y)| energies[&(x, y)])
+                    .min()
+                    .unwrap_or(0);
+                *energies.get_mut(&(x, y)).unwrap() += emin;
+            }
+        }
+
+        let (y_seed, _) = (0..self.width)
+            .map(|x| (x, energies[&(x, self.height - 1)]))
+            .min_by_key(|(_, e)| *e)
+            .unwrap();
+        let mut min_seam = vec![y_seed];
+        for y in 0..(self.height - 1) {
+            let (x, _) = (-1..=1)
+                .filter_map(|dx| self.offset(min_seam[y], self.height - y - 1, dx, -1))
+                .map(|(x2, y2)| (x2, energies[&(x2, y2)]))
+                .min_by_key(|(_, e)| *e)
+                .unwrap();
+            min_seam.push(x);
+        }
+
         self.remove_seam(&min_seam)
     }
 
-    pub fn load(path: impl AsRef<Path>) -> image::ImageResult<Self> {
+    /// Carves out `n` seams from an image.
+    ///
+    /// Equivalent to calling [`Image::carve`] `n` times, except that the initial energies
+    /// are only recomputed near each removed seam rather than across the whole image.
+    pub fn carve_incremental(&self, n: usize) -> Self {
+        let mut img = self.clone();
+        let mut initial = img.compute_initial_energy();
+        for _ in 0..n {
+            let mut energies = initial.clone();
+            img.propagate_energy(&mut energies);
+            let min_seam = img.find_seam(&energies);
+            img.remove_seam_in_place(&min_seam);
+            initial = img.update_initial_energy(&initial, &min_seam);
+        }
+        img
+    }
+
+    /// Carves out `n` seams from an image, producing the same image as calling [`Image::carve`]
+    /// `n` times.
+    ///
+    /// Each call to `carve` allocates a new image, energy matrix and seam. Here, each step
+    /// instead removes the seam from one of two pixel buffers into the other, then swaps them,
+    /// so the buffers shrink in logical width but keep their original capacity. The energies
+    /// and seam are likewise written into buffers that are reused across steps (see
+    /// [`Image::remove_seam_in_place`] for the single-buffer version of the seam removal).
+    ///
+    /// As a result, the number of allocations is constant in `n`: 4 in total, versus 3 per
+    /// seam for the `carve` loop. With a counting global allocator, carving 50 seams from
+    /// `castle.jpg` measured 150 allocations for the `carve` loop and 4 here.
+    ///
+    /// Panics if `n` is larger than the image's width.
+    pub fn carve_double_buffered(&self, n: usize) -> Self {
+        assert!(
+            n <= self.width,
+            "cannot carve {n} seams from a {}-pixel-wide image",
+            self.width
+        );
+        let mut front = self.clone();
+        let mut back = Image {
+            pixels: Vec::with_capacity(self.pixels.len()),
+            width: 0,
+            height: 0,
+        };
+        let mut energies = Matrix::new(self.width, self.height);
+        let mut seam = Vec::with_capacity(self.height);
+        for _ in 0..n {
+            front.compute_initial_energy_into(&mut energies);
+            front.propagate_energy(&mut energies);
+            front.find_seam_into(&energies, 0, &mut seam);
+            front.remove_seam_into(&seam, &mut back);
+            mem::swap(&mut front, &mut back);
+        }
+        front
+    }
+
+    /// Adds a border of `amount` pixels to every side of the image, filled according to `mode`.
+    /// The border can be removed afterwards with [`Image::crop`].
+    ///
+    /// Panics if the image has no pixels.
+    pub fn pad(&self, amount: usize, mode: PadMode) -> Self {
+        assert!(
+            self.width > 0 && self.height > 0,
+            "cannot pad a {}x{} image",
+            self.width,
+            self.height
+        );
+        let width = self.width + 2 * amount;
+        let height = self.height + 2 * amount;
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let y2 = mode.index(y as isize - amount as isize, self.height);
+            for x in 0..width {
+                let x2 = mode.index(x as isize - amount as isize, self.width);
+                pixels.push(self.get(x2, y2));
+            }
+        }
+        Image {
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the `width` x `height` region of the image whose top-left corner is (x, y).
+    ///
+    /// Panics if the region extends past the edge of the image.
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Self {
+        assert!(
+            x + width <= self.width && y + height <= self.height,
+            "cannot crop a {width}x{height} region at ({x}, {y}) from a {}x{} image",
+            self.width,
+            self.height
+        );
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in y..y + height {
+            let start = x + y * self.width;
+            pixels.extend_from_slice(&self.pixels[start..start + width]);
+        }
+        Image {
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    /// Copies the pixels of `other` onto the image, with the top-left corner of `other` at (x, y).
+    /// Any part of `other` that extends past the edge of the image is clipped.
+    pub fn paste(&mut self, other: &Self, x: usize, y: usize) {
+        let width = other.width.min(self.width.saturating_sub(x));
+        let height = other.height.min(self.height.saturating_sub(y));
+        if width == 0 || height == 0 {
+            return;
+        }
+        for row in 0..height {
+            let src = row * other.width;
+            let dst = x + (y + row) * self.width;
+            self.pixels[dst..dst + width].copy_from_slice(&other.pixels[src..src + width]);
+        }
+    }
+
+    /// Swaps the rows and columns of the image, so pixel (x, y) moves to (y, x).
+    pub fn transpose(&self) -> Self {
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        for x in 0..self.width {
+            for y in 0..self.height {
+                pixels.push(self.get(x, y));
+            }
+        }
+        Image {
+            pixels,
+            width: self.height,
+            height: self.width,
+        }
+    }
+
+    /// Rotates the image 90 degrees clockwise, so the left column becomes the top row.
+    pub fn rotate90(&self) -> Self {
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        for x in 0..self.width {
+            for y in (0..self.height).rev() {
+                pixels.push(self.get(x, y));
+            }
+        }
+        Image {
+            pixels,
+            width: self.height,
+            height: self.width,
+        }
+    }
+
+    /// Rotates the image 180 degrees.
+    pub fn rotate180(&self) -> Self {
+        // Rotating by 180 degrees reverses the row-major order of the pixels
+        let mut img = self.clone();
+        img.pixels.reverse();
+        img
+    }
+
+    /// Rotates the image 270 degrees clockwise (i.e. 90 degrees counterclockwise),
+    /// so the right column becomes the top row.
+    pub fn rotate270(&self) -> Self {
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        for x in (0..self.width).rev() {
+            for y in 0..self.height {
+                pixels.push(self.get(x, y));
+            }
+        }
+        Image {
+            pixels,
+            width: self.height,
+            height: self.width,
+        }
+    }
+
+    /// Carves out the single lowest-energy horizontal seam from an image, reducing its height by one.
+    ///
+    /// Panics if the image has no pixels.
+    pub fn carve_horizontal(&self) -> Self {
+        self.transpose().carve().transpose()
+    }
+
+    /// Carves out vertical seams until the image is `width` pixels wide.
+    ///
+    /// Panics if `width` is larger than the image's width.
+    pub fn carve_to_width(&self, width: usize) -> Self {
+        self.carve_to_width_energy(width).0
+    }
+
+    /// Like [`Image::carve_to_width`], but also returns the total energy of the removed seams.
+    /// A lower total means that less important content was removed, so it's a rough measure
+    /// of the quality of the carve.
+    ///
+    /// Like the energies themselves, the total is a `u64` so that it can't overflow on 32-bit targets.
+    ///
+    /// Panics if `width` is larger than the image's width.
+    pub fn carve_to_width_energy(&self, width: usize) -> (Self, u64) {
+        assert!(
+            width <= self.width,
+            "cannot carve a {}-pixel-wide image to {width} pixels",
+            self.width
+        );
+        let mut img = self.clone();
+        let mut initial = img.compute_initial_energy();
+        let mut total = 0;
+        for _ in width..self.width {
+            let mut energies = initial.clone();
+            img.propagate_energy(&mut energies);
+            let min_seam = img.find_seam(&energies);
+            // The seam starts from the bottom row, whose propagated energy is the seam's total
+            total += energies.get(min_seam[0], img.height - 1);
+            img.remove_seam_in_place(&min_seam);
+            initial = img.update_initial_energy(&initial, &min_seam);
+        }
+        (img, total)
+    }
+
+    /// Finds the lowest-energy vertical seam, returning it along with its total energy.
+    fn cheapest_seam(&self) -> (Vec<usize>, u64) {
+        let mut energies = self.compute_initial_energy();
+        self.propagate_energy(&mut energies);
+        let seam = self.find_seam(&energies);
+        // The seam starts from the bottom row, whose propagated energy is the seam's total
+        let cost = energies.get(seam[0], self.height - 1);
+        (seam, cost)
+    }
+
+    /// Carves seams out of the image until it fits within `max_width` x `max_height`.
+    ///
+    /// While the image is too large in both dimensions, each step removes whichever of the
+    /// cheapest vertical and cheapest horizontal seam has the lower energy per pixel, so
+    /// neither direction is distorted more than it needs to be.
+    ///
+    /// Panics if `max_width` or `max_height` is 0.
+    pub fn carve_within(&self, max_width: usize, max_height: usize) -> Self {
+        assert!(
+            max_width > 0 && max_height > 0,
+            "cannot carve an image to fit in a {max_width}x{max_height} box"
+        );
+        let mut img = self.clone();
+        loop {
+            img = match (img.width > max_width, img.height > max_height) {
+                (false, false) => return img,
+                (true, false) => img.carve(),
+                (false, true) => img.carve_horizontal(),
+                (true, true) => {
+                    let (v_seam, v_cost) = img.cheapest_seam();
+                    let transposed = img.transpose();
+                    let (h_seam, h_cost) = transposed.cheapest_seam();
+                    // Compare v_cost / height against h_cost / width without dividing
+                    if v_cost as u128 * img.width as u128 <= h_cost as u128 * img.height as u128 {
+                        img.remove_seam(&v_seam)
+                    } else {
+                        transposed.remove_seam(&h_seam).transpose()
+                    }
+                }
+            };
+        }
+    }
+
+    /// Convolves the image with a 3x3 `kernel`, where `kernel[1][1]` weights the pixel itself.
+    ///
+    /// Each channel is convolved separately. Pixels past the border are clamped to the
+    /// nearest pixel in the image, and the results are rounded and clamped to 0-255.
+    pub fn convolve(&self, kernel: [[f32; 3]; 3]) -> Self {
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        let mut bytes = Vec::with_capacity(P::BYTES);
+        let mut sums = vec![0.; P::BYTES];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                sums.fill(0.);
+                for (ky, row) in kernel.iter().enumerate() {
+                    let y2 = (y + ky).saturating_sub(1).min(self.height - 1);
+                    for (kx, weight) in row.iter().enumerate() {
+                        let x2 = (x + kx).saturating_sub(1).min(self.width - 1);
+                        bytes.clear();
+                        self.get(x2, y2).write_bytes(&mut bytes);
+                        for (sum, byte) in sums.iter_mut().zip(&bytes) {
+                            *sum += weight * *byte as f32;
+                        }
+                    }
+                }
+                bytes.clear();
+                bytes.extend(sums.iter().map(|sum| sum.round().clamp(0., 255.) as u8));
+                pixels.push(P::from_bytes(&bytes));
+            }
+        }
+        Image {
+            pixels,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Blurs the image with a 3x3 Gaussian kernel.
+    pub fn blur(&self) -> Self {
+        self.convolve([
+            [1. / 16., 2. / 16., 1. / 16.],
+            [2. / 16., 4. / 16., 2. / 16.],
+            [1. / 16., 2. / 16., 1. / 16.],
+        ])
+    }
+
+    /// Sharpens the image by subtracting the four adjacent pixels from the center.
+    pub fn sharpen(&self) -> Self {
+        self.convolve([[0., -1., 0.], [-1., 5., -1.], [0., -1., 0.]])
+    }
+
+    /// Applies gamma correction to each channel, i.e. `out = 255 * (in / 255)^g`. A gamma
+    /// greater than 1 darkens mid-tones, and a gamma less than 1 brightens them.
+    pub fn gamma(&self, g: f32) -> Self {
+        // There are only 256 possible inputs, so compute each output once
+        let mut table = [0; 256];
+        for (i, out) in table.iter_mut().enumerate() {
+            *out = (255. * (i as f32 / 255.).powf(g)).round().clamp(0., 255.) as u8;
+        }
+
+        let mut bytes = Vec::with_capacity(P::BYTES);
+        let pixels = self
+            .pixels
+            .iter()
+            .map(|px| {
+                bytes.clear();
+                px.write_bytes(&mut bytes);
+                for byte in bytes.iter_mut() {
+                    *byte = table[*byte as usize];
+                }
+                P::from_bytes(&bytes)
+            })
+            .collect();
+        Image {
+            pixels,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Computes the mean absolute difference between the channels of `self` and `other`, or
+    /// returns None if the images have different dimensions. Identical images have a difference
+    /// of 0, and the largest possible difference is 255.
+    pub fn diff(&self, other: &Self) -> Option<f64> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return None;
+        }
+        let (mut a, mut b) = (Vec::new(), Vec::new());
+        for (p1, p2) in self.pixels.iter().zip(&other.pixels) {
+            p1.write_bytes(&mut a);
+            p2.write_bytes(&mut b);
+        }
+        let total = a
+            .iter()
+            .zip(&b)
+            .map(|(x, y)| x.abs_diff(*y) as u64)
+            .sum::<u64>();
+        Some(total as f64 / a.len().max(1) as f64)
+    }
+
+    /// Renders the image as text, mapping the average intensity of each block of pixels to a
+    /// character from a ramp running from dark to light. The image is downsampled to be at most
+    /// `max_width` characters wide, and rows are halved since characters are about twice as tall
+    /// as they are wide. Every line, including the last, ends with a newline.
+    pub fn to_ascii(&self, max_width: usize) -> String {
+        const RAMP: &[u8] = b" .:-=+*#%@";
+        let cols = self.width.min(max_width);
+        if cols == 0 || self.height == 0 {
+            return String::new();
+        }
+        let rows = ((self.height * cols) as f64 / self.width as f64 / 2.)
+            .round()
+            .max(1.) as usize;
+
+        let mut ascii = String::with_capacity((cols + 1) * rows);
+        let mut bytes = Vec::new();
+        for row in 0..rows {
+            let (y1, y2) = (row * self.height / rows, (row + 1) * self.height / rows);
+            for col in 0..cols {
+                let (x1, x2) = (col * self.width / cols, (col + 1) * self.width / cols);
+                bytes.clear();
+                for y in y1..y2 {
+                    for x in x1..x2 {
+                        self.get(x, y).write_bytes(&mut bytes);
+                    }
+                }
+                let avg = bytes.iter().map(|b| *b as usize).sum::<usize>() / bytes.len();
+                ascii.push(RAMP[avg * RAMP.len() / 256] as char);
+            }
+            ascii.push('\n');
+        }
+        ascii
+    }
+
+    /// Loads an image from `path`. A missing or unreadable file is a [`week_error::Error::Io`],
+    /// and a file that can't be decoded is a [`week_error::Error::Image`].
+    pub fn load(path: impl AsRef<Path>) -> week_error::Result<Self> {
         let path = path.as_ref();
         let img = image::io::Reader::open(path)?.decode()?;
-        let img = image::imageops::colorops::grayscale(&img);
+        let pixels = P::decode(&img)
+            .chunks_exact(P::BYTES)
+            .map(P::from_bytes)
+            .collect();
         Ok(Image {
             width: img.width() as usize,
             height: img.height() as usize,
-            pixels: img.as_bytes().to_vec(),
+            pixels,
         })
     }
 
-    pub fn save(&self, path: impl AsRef<Path>) -> image::ImageResult<()> {
+    pub fn save(&self, path: impl AsRef<Path>) -> week_error::Result<()> {
         let path = path.as_ref();
-        let mut img = image::GrayImage::new(self.width as u32, self.height as u32);
-        for x in 0..self.width {
-            for y in 0..self.height {
-                img.put_pixel(x as u32, y as u32, image::Luma([self.get(x, y)]))
+        let mut buf = Vec::with_capacity(self.pixels.len() * P::BYTES);
+        for px in &self.pixels {
+            px.write_bytes(&mut buf);
+        }
+        image::save_buffer(
+            path,
+            &buf,
+            self.width as u32,
+            self.height as u32,
+            P::COLOR_TYPE,
+        )?;
+        Ok(())
+    }
+}
+
+/// How [`Image::pad`] fills the border around an image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PadMode {
+    /// Repeat the pixels on the edge of the image, e.g. `abc` becomes `aa|abc|cc`.
+    Replicate,
+    /// Reflect the image across its edge pixels, e.g. `abc` becomes `cb|abc|ba`.
+    Mirror,
+}
+
+impl PadMode {
+    /// Maps a possibly out-of-bounds index `i` to an index in `0..n`.
+    fn index(self, i: isize, n: usize) -> usize {
+        let last = n as isize - 1;
+        match self {
+            PadMode::Replicate => i.clamp(0, last) as usize,
+            PadMode::Mirror if n == 1 => 0,
+            PadMode::Mirror => {
+                // Reflections repeat with a period of 2(n - 1)
+                let i = i.rem_euclid(2 * last);
+                (if i > last { 2 * last - i } else { i }) as usize
+            }
+        }
+    }
+}
+
+/// Weightings for converting color pixels to grayscale.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GrayMode {
+    /// The ITU-R BT.601 luma weights, used by standard-definition video.
+    Bt601,
+    /// The ITU-R BT.709 luma weights, used by HD video and sRGB.
+    Bt709,
+    /// The unweighted average of the red, green, and blue channels.
+    Average,
+}
+
+impl GrayMode {
+    /// The weights of the red, green, and blue channels, which sum to 1.
+    fn weights(self) -> [f32; 3] {
+        match self {
+            GrayMode::Bt601 => [0.299, 0.587, 0.114],
+            GrayMode::Bt709 => [0.2126, 0.7152, 0.0722],
+            GrayMode::Average => [1. / 3.; 3],
+        }
+    }
+}
+
+/// A way of computing the initial energy of each pixel in an image, for [`Image::carve_with`].
+pub trait EnergyFn<P: Pixel> {
+    /// Computes the initial energies of `img`, before they are propagated down the image.
+    ///
+    /// The returned grid must have the same width and height as `img`, e.g. by starting from
+    /// [`Matrix::new`] or [`Image::compute_initial_energy`].
+    fn energies(&self, img: &Image<P>) -> Matrix;
+}
+
+/// The gradient magnitude energy used by [`Image::carve`], i.e. [`Image::compute_initial_energy`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Gradient;
+
+impl<P: Pixel> EnergyFn<P> for Gradient {
+    fn energies(&self, img: &Image<P>) -> Matrix {
+        img.compute_initial_energy()
+    }
+}
+
+/// The gradient magnitude energy plus `weight` times the local entropy of each pixel.
+///
+/// The entropy is estimated by the variance of the [intensities](Pixel::intensity) in the
+/// 5x5 window around each pixel (clipped at the image's borders). Its square root, the standard
+/// deviation, is added so that the term is on the same scale as the gradient. Unlike the
+/// gradient, which only sees immediate neighbors, this also gives energy to pixels in a textured
+/// region, so seams are steered through smooth areas rather than across fine detail.
+#[derive(Clone, Copy, Debug)]
+pub struct GradientEntropy {
+    pub weight: f64,
+}
+
+impl<P: Pixel> EnergyFn<P> for GradientEntropy {
+    fn energies(&self, img: &Image<P>) -> Matrix {
+        let mut energies = img.compute_initial_energy();
+        for y in 0..img.height {
+            for x in 0..img.width {
+                let (mut sum, mut sum_sq, mut count) = (0., 0., 0.);
+                for dy in -2..=2 {
+                    for dx in -2..=2 {
+                        if let Some((x2, y2)) = img.offset(x, y, dx, dy) {
+                            let v = img.get(x2, y2).intensity();
+                            sum += v;
+                            sum_sq += v * v;
+                            count += 1.;
+                        }
+                    }
+                }
+                let mean = sum / count;
+                // Rounding can make the variance of a uniform window slightly negative
+                let variance = (sum_sq / count - mean * mean).max(0.);
+                let entropy = variance.sqrt();
+                let energy = energies.get(x, y) + (self.weight * entropy).round() as u64;
+                energies.set(x, y, energy);
+            }
+        }
+        energies
+    }
+}
+
+impl Image<Luma8> {
+    /// Loads a grayscale image like [`Image::load`], but converts color to grayscale
+    /// using the weighting given by `mode`.
+    pub fn load_with(path: impl AsRef<Path>, mode: GrayMode) -> week_error::Result<Self> {
+        let path = path.as_ref();
+        let img = image::io::Reader::open(path)?.decode()?;
+        let [wr, wg, wb] = mode.weights();
+        let pixels = img
+            .to_rgb8()
+            .pixels()
+            .map(|image::Rgb([r, g, b])| {
+                let luma = wr * (*r as f32) + wg * (*g as f32) + wb * (*b as f32);
+                luma.round().clamp(0., 255.) as u8
+            })
+            .collect();
+        Ok(Image {
+            width: img.width() as usize,
+            height: img.height() as usize,
+            pixels,
+        })
+    }
+
+    /// Counts the number of pixels with each intensity.
+    pub fn histogram(&self) -> [u32; 256] {
+        let mut counts = [0; 256];
+        for px in &self.pixels {
+            counts[*px as usize] += 1;
+        }
+        counts
+    }
+
+    /// Linearly stretches the intensities of the image so the darkest pixel becomes 0 and
+    /// the brightest becomes 255. An image with a single intensity is returned unchanged.
+    pub fn auto_contrast(&self) -> Self {
+        let histogram = self.histogram();
+        let min = histogram.iter().position(|n| *n > 0);
+        let max = histogram.iter().rposition(|n| *n > 0);
+        let (min, max) = match (min, max) {
+            (Some(min), Some(max)) if min < max => (min as f32, max as f32),
+            _ => return self.clone(),
+        };
+        let pixels = self
+            .pixels
+            .iter()
+            .map(|px| ((*px as f32 - min) * 255. / (max - min)).round() as u8)
+            .collect();
+        Image {
+            pixels,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+#[cfg(feature = "simd")]
+impl Image<Luma8> {
+    /// Generates the same energies as [`Image::compute_initial_energy`], but computes the
+    /// energies of four adjacent pixels at a time using the `Vec4` operations from week 1.
+    ///
+    /// Pixels on the border of the image have fewer than nine neighbors (including themselves), so only interior
+    /// pixels are vectorized. The border, and any leftover pixels at the end of each row,
+    /// use the scalar computation.
+    ///
+    /// This method requires the `simd` feature, e.g. `cargo bench --features simd`.
+    pub fn compute_initial_energy_simd(&self) -> Energies {
+        use week1::p2_vec::{vec4_abs, vec4_add, vec4_mul};
+
+        let mut energies = Matrix::new(self.width, self.height);
+        let load = |x: usize, y: usize| -> [f64; 4] {
+            let i = x + y * self.width;
+            let px = &self.pixels[i..i + 4];
+            [px[0] as f64, px[1] as f64, px[2] as f64, px[3] as f64]
+        };
+
+        for y in 0..self.height {
+            let mut x = 0;
+            if y > 0 && y < self.height - 1 {
+                // Handle the left border with the scalar computation
+                energies.set(0, y, self.pixel_energy(0, y));
+                x = 1;
+                while x + 4 < self.width {
+                    let px = load(x, y);
+                    let mut diff_sum = [0.; 4];
+                    for dy in [y - 1, y, y + 1] {
+                        for dx in [x - 1, x, x + 1] {
+                            let neighbor = vec4_mul(load(dx, dy), [-1.; 4]);
+                            diff_sum = vec4_add(diff_sum, vec4_abs(vec4_add(px, neighbor)));
+                        }
+                    }
+                    for (i, sum) in diff_sum.iter().enumerate() {
+                        energies.set(x + i, y, *sum as u64 / 9);
+                    }
+                    x += 4;
+                }
+            }
+            for x in x..self.width {
+                energies.set(x, y, self.pixel_energy(x, y));
             }
         }
-        img.save(path)
+
+        energies
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+
+    /// Builds a deterministic pseudo-random image so tests don't depend on an image file.
+    fn noise_image<P: Pixel>(width: usize, height: usize) -> Image<P> {
+        let mut state: u32 = 0x2545_f491;
+        let bytes = (0..width * height * P::BYTES)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state >> 24) as u8
+            })
+            .collect::<Vec<_>>();
+        Image {
+            pixels: bytes.chunks_exact(P::BYTES).map(P::from_bytes).collect(),
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn carve_incremental_test() {
+        let img = noise_image::<Luma8>(40, 30);
+        let mut expected = img.clone();
+        for n in 1..=5 {
+            expected = expected.carve();
+            let actual = img.carve_incremental(n);
+            assert_eq!(actual.width, expected.width);
+            assert_eq!(actual.pixels, expected.pixels, "n = {n}");
+        }
+
+        let img = noise_image::<Luma8>(5, 3);
+        let carved = img.carve_incremental(5);
+        assert_eq!((carved.width, carved.height), (0, 3));
+    }
+
+    #[test]
+    fn carve_double_buffered_test() {
+        let img = noise_image::<Rgb8>(40, 30);
+        let mut expected = img.clone();
+        for n in 0..=8 {
+            let actual = img.carve_double_buffered(n);
+            assert_eq!((actual.width, actual.height), (40 - n, 30));
+            assert_eq!(actual.pixels, expected.pixels, "n = {n}");
+            expected = expected.carve();
+        }
+
+        let img = noise_image::<Luma8>(5, 3);
+        assert_eq!(img.carve_double_buffered(5).pixels.len(), 0);
+    }
+
+    #[test]
+    fn transpose_test() {
+        let img = noise_image::<Rgb8>(7, 4);
+        let t = img.transpose();
+        assert_eq!((t.width, t.height), (4, 7));
+        assert_eq!(t.get(3, 5), img.get(5, 3));
+        assert_eq!(t.transpose().pixels, img.pixels);
+
+        let carved = img.carve_horizontal();
+        assert_eq!((carved.width, carved.height), (7, 3));
+
+        let carved = img.carve_to_width(3);
+        assert_eq!((carved.width(), carved.height()), (3, 4));
+        assert_eq!(img.carve_to_width(7).pixels, img.pixels);
+        let carved = img.carve_to_width(0);
+        assert_eq!((carved.width(), carved.height()), (0, 4));
+    }
+
+    #[test]
+    fn paste_test() {
+        let black = Image::<Luma8>::from_raw(5, 4, vec![0; 20]);
+        let white = Image::<Luma8>::from_raw(2, 2, vec![255; 4]);
+
+        let mut img = black.clone();
+        img.paste(&white, 1, 1);
+        assert_eq!(img.crop(1, 1, 2, 2).pixels, white.pixels);
+        assert_eq!(img.pixels.iter().filter(|px| **px == 255).count(), 4);
+
+        // Only the top-left pixel of the square fits in the corner
+        let mut img = black.clone();
+        img.paste(&white, 4, 3);
+        assert_eq!(img.get(4, 3), 255);
+        assert_eq!(img.pixels.iter().filter(|px| **px == 255).count(), 1);
+
+        // Pasting entirely outside the image does nothing
+        let mut img = black.clone();
+        img.paste(&white, 5, 0);
+        img.paste(&white, 0, 10);
+        img.paste(&white, 100, 0);
+        img.paste(&white, 6, 3);
+        assert_eq!(img.pixels, black.pixels);
+    }
+
+    #[test]
+    fn rotate_test() {
+        #[rustfmt::skip]
+        let img = Image::<Luma8>::from_raw(3, 2, vec![
+            1, 2, 3,
+            4, 5, 6,
+        ]);
+        let r90 = img.rotate90();
+        assert_eq!((r90.width, r90.height), (2, 3));
+        assert_eq!(r90.pixels, [4, 1, 5, 2, 6, 3]);
+        let r180 = img.rotate180();
+        assert_eq!((r180.width, r180.height), (3, 2));
+        assert_eq!(r180.pixels, [6, 5, 4, 3, 2, 1]);
+        let r270 = img.rotate270();
+        assert_eq!((r270.width, r270.height), (2, 3));
+        assert_eq!(r270.pixels, [3, 6, 2, 5, 1, 4]);
+
+        let img = noise_image::<Rgb8>(7, 4);
+        let mut rotated = img.clone();
+        for _ in 0..4 {
+            rotated = rotated.rotate90();
+        }
+        assert_eq!(rotated.pixels, img.pixels);
+        assert_eq!(img.rotate90().rotate90().pixels, img.rotate180().pixels);
+        assert_eq!(img.rotate180().rotate90().pixels, img.rotate270().pixels);
+        assert_eq!(img.rotate270().rotate90().pixels, img.pixels);
+    }
+
+    #[test]
+    fn carve_to_width_energy_test() {
+        let img = noise_image::<Luma8>(40, 30);
+        let (carved, total) = img.carve_to_width_energy(30);
+        assert_eq!(carved.pixels, img.carve_to_width(30).pixels);
+        assert_eq!(img.carve_to_width_energy(30).1, total);
+        assert_eq!(total, 14952);
+
+        // The total is the sum of the costs of removing each seam in turn
+        let mut expected = 0;
+        let mut step = img.clone();
+        for _ in 0..10 {
+            expected += step.cheapest_seam().1;
+            step = step.carve();
+        }
+        assert_eq!(total, expected);
+
+        let (same, none) = img.carve_to_width_energy(40);
+        assert_eq!(same.pixels, img.pixels);
+        assert_eq!(none, 0);
+    }
+
+    #[test]
+    fn carve_with_test() {
+        let img = Image::<Luma8>::load("castle.jpg")
+            .unwrap()
+            .crop(100, 100, 60, 40);
+        assert_eq!(img.carve_with(&Gradient).pixels, img.carve().pixels);
+
+        let energy = GradientEntropy { weight: 0.5 };
+        let mut carved = img.clone();
+        for _ in 0..5 {
+            carved = carved.carve_with(&energy);
+        }
+        assert_eq!((carved.width(), carved.height()), (55, 40));
+        assert_eq!(carved.pixels.len(), 55 * 40);
+
+        // With no weight, the entropy has no effect
+        let no_entropy = GradientEntropy { weight: 0. };
+        assert_eq!(no_entropy.energies(&img).data, Gradient.energies(&img).data);
+        let (gradient, entropy) = (Gradient.energies(&img), energy.energies(&img));
+        assert!(entropy.data.iter().zip(&gradient.data).all(|(e, g)| e >= g));
+
+        // A single bright pixel in the middle of a 5x5 window: the intensities have a mean
+        // of 10 and a mean square of 2500, so a variance of 2400 and a standard deviation of 49
+        let mut spot = Image::<Luma8>::from_raw(7, 7, vec![0; 49]);
+        spot.set(3, 3, 250);
+        let (gradient, entropy) = (
+            Gradient.energies(&spot),
+            GradientEntropy { weight: 1. }.energies(&spot),
+        );
+        assert_eq!(entropy.get(3, 3) - gradient.get(3, 3), 49);
+        // The window around the corner only holds 0s, so it has no variance
+        assert_eq!(entropy.get(0, 0), gradient.get(0, 0));
+
+        let empty = Image::<Luma8>::from_raw(0, 3, vec![]);
+        assert!(empty.try_carve_with(&energy).is_err());
+    }
+
+    #[test]
+    fn carve_within_test() {
+        let img = noise_image::<Luma8>(40, 20);
+        let fitted = img.carve_within(16, 16);
+        assert_eq!((fitted.width, fitted.height), (16, 16));
+
+        // An image that already fits is unchanged
+        let same = img.carve_within(50, 20);
+        assert_eq!((same.width, same.height), (40, 20));
+        assert_eq!(same.pixels, img.pixels);
+
+        // Only the width needs to change to fit in a tall box
+        let narrow = img.carve_within(10, 30);
+        assert_eq!((narrow.width, narrow.height), (10, 20));
+    }
+
+    #[test]
+    fn auto_contrast_test() {
+        // A low-contrast gradient between 100 and 150
+        let img: Image = Image {
+            pixels: (0..60).map(|i| 100 + (i % 51) as u8).collect(),
+            width: 10,
+            height: 6,
+        };
+        let histogram = img.histogram();
+        assert_eq!(histogram.iter().sum::<u32>(), 60);
+        assert_eq!(histogram[100], 2);
+        assert_eq!(histogram[99], 0);
+
+        let stretched = img.auto_contrast();
+        let stretched_histogram = stretched.histogram();
+        assert_eq!(stretched_histogram[0], 2);
+        assert_eq!(stretched_histogram[255], 1);
+        assert_eq!(*stretched.pixels.iter().min().unwrap(), 0);
+        assert_eq!(*stretched.pixels.iter().max().unwrap(), 255);
+
+        let flat: Image = Image {
+            pixels: vec![7; 4],
+            width: 2,
+            height: 2,
+        };
+        assert_eq!(flat.auto_contrast().pixels, flat.pixels);
+    }
+
+    #[test]
+    fn propagate_energy_overflow_test() {
+        // A tall image where every pixel has an energy of 2^31, so the propagated energy
+        // of the bottom row is far beyond u32::MAX
+        const HEIGHT: usize = 1000;
+        let img = noise_image::<Luma8>(3, HEIGHT);
+        let mut energies = Matrix::new(3, HEIGHT);
+        energies.data.fill(1 << 31);
+        img.propagate_energy(&mut energies);
+
+        let total = HEIGHT as u64 * (1 << 31);
+        assert!(total > u32::MAX as u64);
+        assert!(energies.row(HEIGHT - 1).iter().all(|e| *e == total));
+        assert_eq!(img.find_seam(&energies).len(), HEIGHT);
+    }
+
+    #[test]
+    fn pad_test() {
+        let img: Image = Image {
+            pixels: vec![1, 2, 3, 4, 5, 6],
+            width: 3,
+            height: 2,
+        };
+
+        let padded = img.pad(2, PadMode::Replicate);
+        assert_eq!((padded.width, padded.height), (7, 6));
+        #[rustfmt::skip]
+        assert_eq!(padded.pixels, vec![
+            1, 1, 1, 2, 3, 3, 3,
+            1, 1, 1, 2, 3, 3, 3,
+            1, 1, 1, 2, 3, 3, 3,
+            4, 4, 4, 5, 6, 6, 6,
+            4, 4, 4, 5, 6, 6, 6,
+            4, 4, 4, 5, 6, 6, 6,
+        ]);
+        assert_eq!(padded.crop(2, 2, 3, 2).pixels, img.pixels);
+
+        let mirrored = img.pad(1, PadMode::Mirror);
+        #[rustfmt::skip]
+        assert_eq!(mirrored.pixels, vec![
+            5, 4, 5, 6, 5,
+            2, 1, 2, 3, 2,
+            5, 4, 5, 6, 5,
+            2, 1, 2, 3, 2,
+        ]);
+        assert_eq!(mirrored.crop(1, 1, 3, 2).pixels, img.pixels);
+
+        let rgb = noise_image::<Rgb8>(5, 4);
+        for mode in [PadMode::Replicate, PadMode::Mirror] {
+            assert_eq!(rgb.pad(3, mode).crop(3, 3, 5, 4).pixels, rgb.pixels);
+        }
+        assert_eq!(img.crop(1, 0, 2, 1).pixels, vec![2, 3]);
+    }
+
+    #[test]
+    fn diff_test() {
+        let img = noise_image::<Rgb8>(20, 10);
+        assert_eq!(img.diff(&img), Some(0.));
+
+        // Shift every row one pixel to the right, wrapping around
+        let mut shifted = img.clone();
+        for y in 0..img.height {
+            for x in 0..img.width {
+                shifted.set((x + 1) % img.width, y, img.get(x, y));
+            }
+        }
+        assert!(img.diff(&shifted).unwrap() > 0.);
+        assert_eq!(img.diff(&shifted), shifted.diff(&img));
+        assert_eq!(img.diff(&img.carve()), None);
+
+        let black: Image = Image {
+            pixels: vec![0; 4],
+            width: 2,
+            height: 2,
+        };
+        let white = Image {
+            pixels: vec![255; 4],
+            ..black.clone()
+        };
+        assert_eq!(black.diff(&white), Some(255.));
+    }
+
+    #[test]
+    fn gamma_test() {
+        let img = noise_image::<Rgb8>(20, 10);
+        assert_eq!(img.gamma(1.0).pixels, img.pixels);
+
+        let img: Image = Image {
+            pixels: vec![0, 64, 128, 192, 255],
+            width: 5,
+            height: 1,
+        };
+        let darker = img.gamma(2.0);
+        assert_eq!(darker.pixels, vec![0, 16, 64, 145, 255]);
+        assert_eq!(img.gamma(0.5).get(2, 0), 181);
+    }
+
+    #[test]
+    fn to_ascii_test() {
+        // A horizontal gradient from black to white
+        let img: Image = Image {
+            pixels: (0..40)
+                .flat_map(|_| (0..80).map(|x| (x * 255 / 79) as u8))
+                .collect(),
+            width: 80,
+            height: 40,
+        };
+        let ascii = img.to_ascii(20);
+        let lines = ascii.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 5);
+        for line in &lines {
+            assert_eq!(line.len(), 20);
+            assert!(line.starts_with(' ') && line.ends_with('@'));
+        }
+
+        // Images narrower than `max_width` aren't upsampled
+        let ascii = noise_image::<Rgb8>(10, 10).to_ascii(100);
+        assert_eq!(ascii.lines().count(), 5);
+        assert!(ascii.lines().all(|line| line.len() == 10));
+        assert_eq!(img.to_ascii(0), "");
+    }
+
+    #[test]
+    fn blur_test() {
+        let max_diff = |img: &Image| {
+            (0..img.height)
+                .flat_map(|y| (1..img.width).map(move |x| (x, y)))
+                .map(|(x, y)| img.get(x, y).energy_diff(&img.get(x - 1, y)))
+                .max()
+                .unwrap()
+        };
+
+        // A black left half next to a white right half
+        let mut img: Image = Image {
+            pixels: vec![0; 8 * 4],
+            width: 8,
+            height: 4,
+        };
+        for y in 0..4 {
+            for x in 4..8 {
+                img.set(x, y, 255);
+            }
+        }
+
+        let blurred = img.blur();
+        assert_eq!(max_diff(&img), 255);
+        assert!(max_diff(&blurred) < max_diff(&img));
+        assert!(blurred.pixels.iter().all(|px| (0..=255).contains(px)));
+        assert_eq!(blurred.get(0, 0), 0);
+        assert_eq!(blurred.get(7, 3), 255);
+
+        let sharpened = blurred.sharpen();
+        assert!(max_diff(&sharpened) > max_diff(&blurred));
+    }
+
+    #[test]
+    #[cfg(feature = "simd")]
+    fn compute_initial_energy_simd_test() {
+        for (width, height) in [(1, 1), (5, 3), (40, 30), (43, 7)] {
+            let img = noise_image::<Luma8>(width, height);
+            let expected = img.compute_initial_energy();
+            let actual = img.compute_initial_energy_simd();
+            assert_eq!(actual.data, expected.data, "{width}x{height}");
+        }
+
+        let img: Image = Image::load("castle.jpg").unwrap();
+        assert_eq!(
+            img.compute_initial_energy_simd().data,
+            img.compute_initial_energy().data
+        );
+    }
+
+    #[test]
+    fn find_seam_fixture_test() {
+        // A bright wall runs down column 2 and then steps over to column 1. The cheapest
+        // seam slips diagonally through the step, staying as far from the wall as it can.
+        #[rustfmt::skip]
+        let img = Image::<Luma8>::from_raw(4, 4, vec![
+            0,  0, 90,  0,
+            0,  0, 90,  0,
+            0, 90,  0,  0,
+            0, 90,  0,  0,
+        ]);
+        let mut energies = img.compute_initial_energy();
+        let rows = |e: &Energies| (0..4).map(|y| e.row(y).to_vec()).collect::<Vec<_>>();
+        assert_eq!(
+            rows(&energies),
+            [
+                [0, 30, 60, 45],
+                [15, 30, 60, 30],
+                [30, 60, 30, 15],
+                [45, 60, 30, 0]
+            ]
+        );
+
+        img.propagate_energy(&mut energies);
+        assert_eq!(energies.row(3), [90, 105, 90, 60]);
+
+        // Seams are listed from the bottom row up
+        assert_eq!(img.find_seam(&energies), [3, 2, 1, 0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn from_raw_wrong_len_test() {
+        Image::<Luma8>::from_raw(4, 4, vec![0; 15]);
+    }
+
+    #[test]
+    fn remove_seam_in_place_test() {
+        let img = noise_image::<Rgb8>(9, 6);
+        for seam in [vec![0; 6], vec![8; 6], vec![3, 4, 5, 4, 3, 2]] {
+            let mut in_place = img.clone();
+            in_place.remove_seam_in_place(&seam);
+            let expected = img.remove_seam(&seam);
+            assert_eq!((in_place.width, in_place.height), (8, 6));
+            assert_eq!(in_place.pixels, expected.pixels, "seam = {seam:?}");
+        }
+
+        let mut one_column = noise_image::<Luma8>(1, 3);
+        one_column.remove_seam_in_place(&[0, 0, 0]);
+        assert_eq!(one_column.width, 0);
+        assert!(one_column.pixels.is_empty());
+    }
+
+    /// Lists every seam of a `width` x `height` image, from the bottom row up like `find_seam`.
+    fn all_seams(width: usize, height: usize) -> Vec<Vec<usize>> {
+        let mut seams = (0..width).map(|x| vec![x]).collect::<Vec<_>>();
+        for _ in 1..height {
+            seams = seams
+                .into_iter()
+                .flat_map(|seam| {
+                    let x = *seam.last().unwrap();
+                    (x.saturating_sub(1)..=(x + 1).min(width - 1)).map(move |x2| {
+                        let mut seam = seam.clone();
+                        seam.push(x2);
+                        seam
+                    })
+                })
+                .collect();
+        }
+        seams
+    }
+
+    /// Sums the initial energies along `seam`, plus `weight` for every move between columns.
+    fn seam_cost(img: &Image<Luma8>, initial: &Energies, seam: &[usize], weight: u64) -> u64 {
+        let energy: u64 = (seam.iter().enumerate())
+            .map(|(i, &x)| initial.get(x, img.height - 1 - i))
+            .sum();
+        let moves = seam.windows(2).filter(|w| w[0] != w[1]).count() as u64;
+        energy + weight * moves
+    }
+
+    #[test]
+    fn find_seam_straight_test() {
+        let img = noise_image::<Luma8>(30, 20);
+        let initial = img.compute_initial_energy();
+        let mut energies = initial.clone();
+        img.propagate_energy(&mut energies);
+
+        let moves = |seam: &[usize]| seam.windows(2).filter(|w| w[0] != w[1]).count();
+        let seam = img.find_seam(&energies);
+        assert_eq!(img.find_seam_straight(&initial, 0), seam);
+
+        let straight = img.find_seam_straight(&initial, 10_000);
+        assert_eq!(straight.len(), img.height);
+        assert_eq!(moves(&straight), 0);
+
+        // The seam is the cheapest under the penalized cost, checked against every seam
+        let img = noise_image::<Luma8>(6, 5);
+        let initial = img.compute_initial_energy();
+        for weight in [0, 20, 50, 10_000] {
+            let straight = img.find_seam_straight(&initial, weight);
+            let best = all_seams(6, 5)
+                .iter()
+                .map(|seam| seam_cost(&img, &initial, seam, weight))
+                .min()
+                .unwrap();
+            assert_eq!(
+                seam_cost(&img, &initial, &straight, weight),
+                best,
+                "weight = {weight}"
+            );
+        }
+    }
+
+    #[test]
+    fn find_seam_avoiding_test() {
+        let img = noise_image::<Luma8>(30, 20);
+        let initial = img.compute_initial_energy();
+        let mut energies = initial.clone();
+        img.propagate_energy(&mut energies);
+
+        let seam = img.find_seam(&energies);
+        for forbidden_x in [seam[0], seam[10], 0, 29] {
+            let avoiding = img.find_seam_avoiding(&initial, forbidden_x);
+            assert_eq!(avoiding.len(), img.height);
+            assert!(
+                !avoiding.contains(&forbidden_x),
+                "forbidden_x = {forbidden_x}"
+            );
+        }
+
+        // The seam is the cheapest of those outside the column, checked against every seam
+        let img = noise_image::<Luma8>(6, 5);
+        let initial = img.compute_initial_energy();
+        for forbidden_x in 0..6 {
+            let avoiding = img.find_seam_avoiding(&initial, forbidden_x);
+            let best = all_seams(6, 5)
+                .iter()
+                .filter(|seam| !seam.contains(&forbidden_x))
+                .map(|seam| seam_cost(&img, &initial, seam, 0))
+                .min()
+                .unwrap();
+            assert!(!avoiding.contains(&forbidden_x));
+            assert_eq!(seam_cost(&img, &initial, &avoiding, 0), best);
+        }
+
+        // A one-column image has no alternative, and the penalty doesn't overflow
+        let column = noise_image::<Luma8>(1, 20);
+        let initial = column.compute_initial_energy();
+        assert_eq!(column.find_seam_avoiding(&initial, 0), vec![0; 20]);
+    }
+
+    #[test]
+    fn try_carve_test() {
+        let empty = Image::<Luma8> {
+            pixels: vec![],
+            width: 0,
+            height: 5,
+        };
+        assert_eq!(
+            empty.try_carve().err(),
+            Some(CarveError::Empty {
+                width: 0,
+                height: 5
+            })
+        );
+
+        let img = noise_image::<Luma8>(1, 5).try_carve().unwrap();
+        assert_eq!((img.width, img.height), (0, 5));
+        assert!(img.try_carve().is_err());
+    }
+
+    #[test]
+    fn carve_hashmap_test() {
+        let mut img = noise_image::<Luma8>(30, 20);
+        for _ in 0..5 {
+            let expected = img.carve();
+            img = img.carve_hashmap();
+            assert_eq!(img.pixels, expected.pixels);
+        }
+    }
+
+    #[test]
+    fn carve_pixel_types_test() {
+        let gray = noise_image::<Luma8>(20, 10).carve().carve();
+        assert_eq!((gray.width, gray.height), (18, 10));
+        assert_eq!(gray.pixels.len(), 18 * 10);
+
+        let rgb = noise_image::<Rgb8>(20, 10).carve().carve();
+        assert_eq!((rgb.width, rgb.height), (18, 10));
+        assert_eq!(rgb.pixels.len(), 18 * 10);
+    }
+
+    #[test]
+    fn load_error_test() {
+        use week_error::Error;
+
+        match Image::<Luma8>::load("missing.jpg") {
+            Err(Error::Io(e)) => assert_eq!(e.kind(), std::io::ErrorKind::NotFound),
+            Err(e) => panic!("expected an I/O error, got {e}"),
+            Ok(_) => panic!("loaded a missing image"),
+        }
+
+        // The process ID keeps concurrent test runs from sharing the file
+        let name = format!("load_error_test_{}.jpg", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, b"not a jpeg").unwrap();
+        let result = Image::<Luma8>::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(Error::Image(_))));
+    }
+
+    #[test]
+    fn load_with_test() {
+        let bt601 = Image::load_with("castle.jpg", GrayMode::Bt601).unwrap();
+        let average = Image::load_with("castle.jpg", GrayMode::Average).unwrap();
+        assert_eq!((bt601.width, bt601.height), (average.width, average.height));
+        assert_ne!(bt601.pixels, average.pixels);
+    }
+
+    #[test]
+    fn img_rgb_test() {
+        let img = Image::<Rgb8>::load("castle.jpg").unwrap();
+        let carved = img.carve();
+        assert_eq!(carved.width, img.width - 1);
+
+        let name = format!("img_rgb_test_{}.jpg", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        carved.save(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     fn img_test() {
-        let mut img = Image::load("input.jpg").unwrap();
+        // input.jpg isn't in the repository, so carve the castle image that is
+        let mut img: Image = Image::load("castle.jpg").unwrap();
 
         for _ in 0..50 {
             img = img.carve();
         }
 
-        img.save("output.jpg").unwrap();
+        // Saving to a file in the repository would overwrite output.jpg
+        let name = format!("img_test_{}.jpg", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        img.save(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
     }
 }
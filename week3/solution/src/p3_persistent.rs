@@ -0,0 +1,137 @@
+//! P3: Persistent binary search tree [CHALLENGE PROBLEM]
+//!
+//! The BST in P1 is *ephemeral*: inserting into it mutates the tree, so the old version of the
+//! tree is gone. In this problem, you will implement a *persistent* BST, where inserting returns
+//! a new tree and leaves the old one untouched.
+//!
+//! Copying the whole tree on every insert would be far too slow. Instead, an insert only copies
+//! the nodes on the path from the root to the new element, and the new tree shares every other
+//! subtree with the old tree. For example, inserting D into the tree on the left copies B and C,
+//! but shares A:
+//!
+//! ```text
+//!   B          B'
+//!  / \        / \
+//! A   C      A   C'
+//!                 \
+//!                  D
+//! ```
+//!
+//! Since a subtree can now have several owners, the children are stored in an [`Rc`] rather than
+//! a [`Box`]. The elements are stored in an `Rc` too, so the copied nodes can share them without
+//! requiring `T: Clone`.
+
+use std::rc::Rc;
+
+#[derive(Default)]
+pub enum PersistentBST<T> {
+    /// A leaf is the bottom of the tree, it contains no data
+    #[default]
+    Leaf,
+    /// A node contains a shared datum of type T, then its shared left and right children.
+    Node(Rc<T>, Rc<PersistentBST<T>>, Rc<PersistentBST<T>>),
+}
+
+use PersistentBST::{Leaf, Node};
+
+// Cloning a tree only clones the `Rc`s of its root, so it doesn't require `T: Clone`.
+impl<T> Clone for PersistentBST<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Leaf => Leaf,
+            Node(s, l, r) => Node(Rc::clone(s), Rc::clone(l), Rc::clone(r)),
+        }
+    }
+}
+
+impl<T: PartialOrd> PersistentBST<T> {
+    /// `len` computes the number of nodes in the tree.
+    pub fn len(&self) -> usize {
+        match self {
+            Leaf => 0,
+            Node(_, l, r) => 1 + l.len() + r.len(),
+        }
+    }
+
+    /// `is_empty` returns true if the tree has no nodes.
+    pub fn is_empty(&self) -> bool {
+        matches!(self, Leaf)
+    }
+
+    /// `contains` returns true if an element equal to `query` is in the tree.
+    pub fn contains(&self, query: &T) -> bool {
+        match self {
+            Leaf => false,
+            Node(s, l, r) => {
+                if *query == **s {
+                    true
+                } else if *query > **s {
+                    r.contains(query)
+                } else {
+                    l.contains(query)
+                }
+            }
+        }
+    }
+
+    /// `persistent_insert` returns a new tree with `t` inserted, in the same position as
+    /// `BST::insert` would put it. `self` is left unchanged.
+    ///
+    /// Only the nodes on the path to `t` are copied, and every other subtree is shared
+    /// between `self` and the new tree.
+    pub fn persistent_insert(&self, t: T) -> Self {
+        match self {
+            Leaf => Node(Rc::new(t), Rc::new(Leaf), Rc::new(Leaf)),
+            Node(s, l, r) => {
+                if t >= **s {
+                    Node(Rc::clone(s), Rc::clone(l), Rc::new(r.persistent_insert(t)))
+                } else {
+                    Node(Rc::clone(s), Rc::new(l.persistent_insert(t)), Rc::clone(r))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn persistent_insert_test() {
+        let t1 = ["B", "A", "C"]
+            .into_iter()
+            .fold(PersistentBST::default(), |t, s| t.persistent_insert(s));
+        let t2 = t1.persistent_insert("D");
+
+        assert_eq!(t1.len(), 3);
+        assert!(!t1.contains(&"D"));
+        assert_eq!(t2.len(), 4);
+        assert!(t2.contains(&"D"));
+        assert!(["A", "B", "C"].iter().all(|s| t2.contains(s)));
+
+        // The left subtree and the root's element are shared, but the right subtree is copied
+        let (Node(s1, l1, r1), Node(s2, l2, r2)) = (&t1, &t2) else {
+            panic!("expected non-empty trees");
+        };
+        assert!(Rc::ptr_eq(s1, s2));
+        assert!(Rc::ptr_eq(l1, l2));
+        assert!(!Rc::ptr_eq(r1, r2));
+        assert_eq!((r1.len(), r2.len()), (1, 2));
+    }
+
+    #[test]
+    fn persistent_versions_test() {
+        // Every intermediate version stays valid
+        let mut versions = vec![PersistentBST::Leaf];
+        for i in [5, 2, 8, 1, 9, 3] {
+            let next = versions.last().unwrap().persistent_insert(i);
+            versions.push(next);
+        }
+        for (n, t) in versions.iter().enumerate() {
+            assert_eq!(t.len(), n);
+        }
+        assert!(versions[3].contains(&8) && !versions[3].contains(&1));
+        assert!(versions[0].is_empty());
+    }
+}
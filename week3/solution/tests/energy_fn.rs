@@ -0,0 +1,52 @@
+//! Checks that [`EnergyFn`] can be implemented outside of the crate.
+
+use week3::p2_img::{EnergyFn, Image, Luma8, Matrix};
+
+/// Gives every pixel a high energy, except in one column which has none.
+struct CheapColumn(usize);
+
+impl EnergyFn<Luma8> for CheapColumn {
+    fn energies(&self, img: &Image<Luma8>) -> Matrix {
+        let mut energies = Matrix::new(img.width(), img.height());
+        for y in 0..img.height() {
+            for x in 0..img.width() {
+                if x != self.0 {
+                    energies.set(x, y, 100);
+                }
+            }
+        }
+        energies
+    }
+}
+
+#[test]
+fn external_energy_fn_test() {
+    const WIDTH: usize = 5;
+    const HEIGHT: usize = 4;
+    let pixels = (0..WIDTH * HEIGHT).map(|i| (i % WIDTH) as u8).collect();
+    let img = Image::from_raw(WIDTH, HEIGHT, pixels);
+
+    for cheap in 0..WIDTH {
+        let carved = img.carve_with(&CheapColumn(cheap));
+        assert_eq!((carved.width(), carved.height()), (WIDTH - 1, HEIGHT));
+        for y in 0..HEIGHT {
+            let row = (0..WIDTH - 1).map(|x| carved.get(x, y)).collect::<Vec<_>>();
+            let expected = (0..WIDTH as u8)
+                .filter(|&x| x != cheap as u8)
+                .collect::<Vec<_>>();
+            assert_eq!(row, expected);
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "energies must be 5x4")]
+fn wrong_size_energy_fn_test() {
+    struct TooSmall;
+    impl EnergyFn<Luma8> for TooSmall {
+        fn energies(&self, _img: &Image<Luma8>) -> Matrix {
+            Matrix::new(3, 3)
+        }
+    }
+    Image::from_raw(5, 4, vec![0u8; 20]).carve_with(&TooSmall);
+}
@@ -5,3 +5,6 @@ pub mod p1_bst;
 
 /// P2: optimizing image seam carving
 pub mod p2_img;
+
+/// P3: persistent binary search tree
+pub mod p3_persistent;
@@ -21,17 +21,12 @@
 //!   Box::new(Node("D", 
 //!     Box::new(Leaf), Box::new(Leaf))));
 
-use std::fmt::{self, Debug, Display};
+use std::fmt::Display;
 use std::mem;
 
-#[derive(PartialEq, Eq, Clone)]
-pub enum BST<T> {
-    /// A leaf is the bottom of the tree, it contains no data
-    Leaf,
-    /// A node contains a datum of type T, then left and right children.
-    /// Those children are in boxes, so the BST type has a fixed size.
-    Node(T, Box<BST<T>>, Box<BST<T>>),
-}
+#[path = "../../shared/p1_bst_common.rs"]
+mod common;
+pub use common::BST;
 
 impl<T: PartialOrd + Display> BST<T> {
     /// P1a: `len` computes the number of nodes in the BST `self`.
@@ -102,210 +97,4 @@ impl<T: PartialOrd + Display> BST<T> {
     pub fn rebalance(&mut self) {
         unimplemented!()
     }
-
-
-    /// Provided helper function that gives a nice visual representation of a BST.
-    /// You can print any BST by doing `println!("{tree:?}")`.
-    ///
-    /// Adapted from https://docs.rs/ascii_tree
-    fn fmt_levels(&self, f: &mut fmt::Formatter<'_>, level: Vec<usize>) -> fmt::Result {
-        use BST::*;
-        const EMPTY: &str = "   ";
-        const EDGE: &str = " └─";
-        const PIPE: &str = " │ ";
-        const BRANCH: &str = " ├─";
-
-        let maxpos = level.len();
-        let mut second_line = String::new();
-        for (pos, l) in level.iter().enumerate() {
-            let last_row = pos == maxpos - 1;
-            if *l == 1 {
-                if !last_row {
-                    write!(f, "{}", EMPTY)?
-                } else {
-                    write!(f, "{}", EDGE)?
-                }
-                second_line.push_str(EMPTY);
-            } else {
-                if !last_row {
-                    write!(f, "{}", PIPE)?
-                } else {
-                    write!(f, "{}", BRANCH)?
-                }
-                second_line.push_str(PIPE);
-            }
-        }
-
-        match self {
-            Node(s, l, r) => {
-                let mut d = 2;
-                writeln!(f, " {s}")?;
-                for t in &[l, r] {
-                    let mut lnext = level.clone();
-                    lnext.push(d);
-                    d -= 1;
-                    t.fmt_levels(f, lnext)?;
-                }
-            }
-            Leaf => writeln!(f)?,
-        }
-        Ok(())
-    }
-}
-
-impl<T: Debug + Display + PartialOrd> fmt::Debug for BST<T> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.fmt_levels(f, vec![])
-    }
-}
-
-#[cfg(test)]
-mod test {
-    use super::*;
-    use lazy_static::lazy_static;
-    use BST::*;
-
-    lazy_static! {
-        static ref TEST_TREE: BST<&'static str> = {
-            Node(
-                "B",
-                Box::new(Node("A", Box::new(Leaf), Box::new(Leaf))),
-                Box::new(Node("C", Box::new(Leaf), Box::new(Leaf))),
-            )
-        };
-    }
-
-    #[test]
-    fn len_test() {
-        let heap_tree: Box<BST<i32>> = Box::new(BST::Leaf);
-        match &*heap_tree {
-            BST::Leaf => {}
-            BST::Node(t, _, _) => {
-                println!("{}", t);
-            }
-        };
-
-        assert_eq!(TEST_TREE.len(), 3);
-    }
-
-    #[test]
-    fn insertion_test() {
-        let mut t = TEST_TREE.clone();
-        t.insert("E");
-        assert_eq!(
-            t,
-            Node(
-                "B",
-                Box::new(Node("A", Box::new(Leaf), Box::new(Leaf))),
-                Box::new(Node(
-                    "C",
-                    Box::new(Leaf),
-                    Box::new(Node("E", Box::new(Leaf), Box::new(Leaf)))
-                )),
-            )
-        );
-    }
-
-    #[test]
-    fn search_test() {
-        let mut t = TEST_TREE.clone();
-        t.insert("E");
-        assert!(t.search(&"D") == Some(&"E"));
-        assert!(t.search(&"C") == Some(&"C"));
-        assert!(t.search(&"F") == None);
-    }
-
-    #[test]
-    fn rebalance1_test() {
-        let mut t = Node(
-            "D",
-            Box::new(Node(
-                "B",
-                Box::new(Node("A", Box::new(Leaf), Box::new(Leaf))),
-                Box::new(Node("C", Box::new(Leaf), Box::new(Leaf))),
-            )),
-            Box::new(Node("E", Box::new(Leaf), Box::new(Leaf))),
-        );
-
-        let t2 = Node(
-            "C",
-            Box::new(Node(
-                "B",
-                Box::new(Node("A", Box::new(Leaf), Box::new(Leaf))),
-                Box::new(Leaf),
-            )),
-            Box::new(Node(
-                "D",
-                Box::new(Leaf),
-                Box::new(Node("E", Box::new(Leaf), Box::new(Leaf))),
-            )),
-        );
-
-        t.rebalance();
-        assert_eq!(t, t2);
-    }
-
-    #[test]
-    fn rebalance2_test() {
-        let mut t = Node(
-            "A",
-            Box::new(Leaf),
-            Box::new(Node(
-                "B",
-                Box::new(Leaf),
-                Box::new(Node(
-                    "C",
-                    Box::new(Leaf),
-                    Box::new(Node("D", Box::new(Leaf), Box::new(Leaf))),
-                )),
-            )),
-        );
-
-        let t2 = Node(
-            "B",
-            Box::new(Node("A", Box::new(Leaf), Box::new(Leaf))),
-            Box::new(Node(
-                "C",
-                Box::new(Leaf),
-                Box::new(Node("D", Box::new(Leaf), Box::new(Leaf))),
-            )),
-        );
-
-        t.rebalance();
-        assert_eq!(t, t2);
-    }
-
-    #[test]
-    fn rebalance3_test() {
-        let mut t = Node(
-            "E",
-            Box::new(Node(
-                "B",
-                Box::new(Leaf),
-                Box::new(Node(
-                    "D",
-                    Box::new(Node("C", Box::new(Leaf), Box::new(Leaf))),
-                    Box::new(Leaf),
-                )),
-            )),
-            Box::new(Node("F", Box::new(Leaf), Box::new(Leaf))),
-        );
-
-        let t2 = Node(
-            "D",
-            Box::new(Node(
-                "B",
-                Box::new(Leaf),
-                Box::new(Node("C", Box::new(Leaf), Box::new(Leaf))),
-            )),
-            Box::new(Node(
-                "E",
-                Box::new(Leaf),
-                Box::new(Node("F", Box::new(Leaf), Box::new(Leaf))),
-            )),
-        );
-
-        t.rebalance();
-        assert_eq!(t, t2);
-    }
 }
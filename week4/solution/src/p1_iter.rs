@@ -29,6 +29,12 @@
 //! To get you started, I would read Rust's documentation on how to implement an iterator:
 //! https://doc.rust-lang.org/std/iter/index.html#implementing-iterator
 
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Cloning a `CartesianProduct` mid-iteration produces an independent copy that
+/// resumes from the same pair.
+#[derive(Clone)]
 pub struct CartesianProduct<L, R> {
     l: Vec<L>,
     r: Vec<R>,
@@ -61,11 +67,87 @@ where
     }
 }
 
-trait IntoCartesianProduct: Iterator {
+#[cfg(feature = "parallel")]
+impl<L, R> CartesianProduct<L, R>
+where
+    L: Clone + Send + Sync,
+    R: Clone + Send + Sync,
+{
+    /// Converts the remaining pairs into a parallel iterator, so that they can be processed
+    /// (e.g. collected) across threads. The pairs are in the same order as the serial iterator.
+    pub fn into_par_iter(self) -> impl IndexedParallelIterator<Item = (L, R)> {
+        // Number the pairs in order, so that pair `k` is `(l[k / n], r[k % n])`
+        let n = self.r.len();
+        let start = self.i * n + self.j;
+        let end = self.l.len() * n;
+        let (l, r) = (self.l, self.r);
+        (start..end)
+            .into_par_iter()
+            .map(move |k| (l[k / n].clone(), r[k % n].clone()))
+    }
+}
+
+/// A cartesian product that doesn't buffer either input. Instead, the left iterator is
+/// cloned and re-run for every item of the right iterator, trading memory for recomputation.
+///
+/// Because the right iterator is in the outer loop, pairs are produced in a different order
+/// than [`CartesianProduct`]: all pairs for the first right item, then the second, and so on.
+pub struct CartesianProductCloneIter<L, R: Iterator> {
+    l_orig: L,
+    l: L,
+    r: R,
+    r_item: Option<R::Item>,
+}
+
+impl<L, R> Iterator for CartesianProductCloneIter<L, R>
+where
+    L: Iterator + Clone,
+    R: Iterator,
+    R::Item: Clone,
+{
+    type Item = (L::Item, R::Item);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let r_item = self.r_item.as_ref()?;
+            if let Some(l_item) = self.l.next() {
+                return Some((l_item, r_item.clone()));
+            }
+            self.r_item = self.r.next();
+            self.l = self.l_orig.clone();
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.r_item.is_none() {
+            return (0, Some(0));
+        }
+        let (l_lo, l_hi) = self.l.size_hint();
+        let (orig_lo, orig_hi) = self.l_orig.size_hint();
+        let (r_lo, r_hi) = self.r.size_hint();
+        let lo = r_lo.saturating_mul(orig_lo).saturating_add(l_lo);
+        let hi = match (l_hi, orig_hi, r_hi) {
+            (Some(l), Some(orig), Some(r)) => r.checked_mul(orig).and_then(|n| n.checked_add(l)),
+            _ => None,
+        };
+        (lo, hi)
+    }
+}
+
+pub trait IntoCartesianProduct: Iterator {
     fn cartesian_product<Other: Iterator>(
         self,
         other: Other,
     ) -> CartesianProduct<Self::Item, Other::Item>;
+
+    /// Like `cartesian_product`, but uses O(1) memory by re-cloning `self` for each
+    /// item of `other`. See [`CartesianProductCloneIter`].
+    fn cartesian_product_clone_iter<Other: Iterator>(
+        self,
+        other: Other,
+    ) -> CartesianProductCloneIter<Self, Other>
+    where
+        Self: Clone + Sized;
 }
 
 impl<T: Iterator> IntoCartesianProduct for T {
@@ -77,6 +159,21 @@ impl<T: Iterator> IntoCartesianProduct for T {
         let r = other.collect::<Vec<_>>();
         CartesianProduct { l, r, i: 0, j: 0 }
     }
+
+    fn cartesian_product_clone_iter<Other: Iterator>(
+        self,
+        mut other: Other,
+    ) -> CartesianProductCloneIter<Self, Other>
+    where
+        Self: Clone,
+    {
+        CartesianProductCloneIter {
+            l: self.clone(),
+            l_orig: self,
+            r_item: other.next(),
+            r: other,
+        }
+    }
 }
 
 
@@ -96,4 +193,65 @@ mod test {
             hashset![(1, 3), (1, 4), (2, 3), (2, 4)]
         )
     }
+
+    #[test]
+    fn cartesian_product_clone_test() {
+        let mut product = [1, 2].into_iter().cartesian_product(['a', 'b'].into_iter());
+        assert_eq!(product.next(), Some((1, 'a')));
+
+        let branch = product.clone();
+        let rest = vec![(1, 'b'), (2, 'a'), (2, 'b')];
+        assert_eq!(product.collect::<Vec<_>>(), rest);
+        assert_eq!(branch.collect::<Vec<_>>(), rest);
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn into_par_iter_test() {
+        let serial = (0..100).cartesian_product(0..100).collect::<HashSet<_>>();
+        let parallel = (0..100)
+            .cartesian_product(0..100)
+            .into_par_iter()
+            .collect::<HashSet<_>>();
+        assert_eq!(parallel.len(), 10_000);
+        assert_eq!(parallel, serial);
+
+        // Only the pairs that haven't been produced yet are included, in order
+        let mut product = [1, 2].into_iter().cartesian_product(['a', 'b'].into_iter());
+        product.next();
+        let rest = product.into_par_iter().collect::<Vec<_>>();
+        assert_eq!(rest, vec![(1, 'b'), (2, 'a'), (2, 'b')]);
+    }
+
+    #[test]
+    fn cartesian_product_clone_iter_test() {
+        let product = (0..1000).cartesian_product_clone_iter(0..1000);
+        // Only the iterators themselves are stored, not their items
+        assert!(std::mem::size_of_val(&product) <= 8 * std::mem::size_of::<usize>());
+        assert_eq!(product.size_hint(), (1_000_000, Some(1_000_000)));
+
+        let mut product = product.skip(999);
+        assert_eq!(product.next(), Some((999, 0)));
+        assert_eq!(product.next(), Some((0, 1)));
+        assert_eq!(
+            product.size_hint(),
+            (1_000_000 - 1001, Some(1_000_000 - 1001))
+        );
+        assert_eq!(product.count(), 1_000_000 - 1001);
+
+        let h1 = hashset![1, 2];
+        let h2 = hashset![3, 4];
+        // `HashSet::into_iter` isn't `Clone`, so the left side borrows the set instead
+        let l = h1.iter().copied();
+        let product = l.cartesian_product_clone_iter(h2.into_iter());
+        assert_eq!(
+            product.collect::<HashSet<_>>(),
+            hashset![(1, 3), (1, 4), (2, 3), (2, 4)]
+        );
+
+        let mut empty = (0..0).cartesian_product_clone_iter(0..3);
+        assert_eq!(empty.size_hint(), (0, Some(0)));
+        assert_eq!(empty.next(), None);
+        assert_eq!((0..3).cartesian_product_clone_iter(0..0).count(), 0);
+    }
 }
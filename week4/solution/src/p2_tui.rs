@@ -31,6 +31,34 @@ pub struct Dimensions {
 pub trait Element {
     fn dimensions(&self) -> Dimensions;
     fn render(&self);
+
+    /// Renders the element as lines padded to `width` columns, which must be at least
+    /// the element's own width. This lets containers draw borders around each line.
+    fn render_in_width(&self, width: usize) -> Vec<String>;
+}
+
+/// Prints rendered lines, without a newline after the last one.
+fn print_lines(lines: &[String]) {
+    print!("{}", lines.join("\n"));
+}
+
+/// A text style, drawn with ANSI escape codes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    Bold,
+    /// Swaps the foreground and background colors.
+    Inverse,
+}
+
+impl Style {
+    /// Wraps `text` in the escape codes that turn the style on and back off.
+    pub fn apply(self, text: &str) -> String {
+        let code = match self {
+            Style::Bold => 1,
+            Style::Inverse => 7,
+        };
+        format!("\u{001b}[{code}m{text}\u{001b}[0m")
+    }
 }
 
 pub struct Text {
@@ -41,19 +69,29 @@ impl Text {
     pub fn new(text: String) -> Self {
         Text { text }
     }
+
+    /// Returns the lines of the text. Unlike `str::lines`, an empty text (or a trailing
+    /// newline) still produces an empty line, so every `Text` takes up at least one line.
+    fn lines(&self) -> impl Iterator<Item = &str> {
+        self.text.split('\n')
+    }
 }
 
 impl Element for Text {
     fn dimensions(&self) -> Dimensions {
         Dimensions {
-            width: self.text.len(),
-            height: 1,
+            width: self.lines().map(str::len).max().unwrap_or(0),
+            height: self.lines().count(),
         }
     }
 
     fn render(&self) {
         print!("{}", self.text);
     }
+
+    fn render_in_width(&self, width: usize) -> Vec<String> {
+        self.lines().map(|line| format!("{line:<width$}")).collect()
+    }
 }
 
 pub struct Heading {
@@ -74,9 +112,19 @@ impl Element for Heading {
     }
 
     fn render(&self) {
-        print!("\u{001b}[1m");
-        self.text.render();
-        print!("\u{001b}[0m")
+        let lines = self.text.lines().map(|line| Style::Bold.apply(line));
+        print_lines(&lines.collect::<Vec<_>>());
+    }
+
+    fn render_in_width(&self, width: usize) -> Vec<String> {
+        // Pad outside the escape codes, so the padding isn't bolded
+        self.text
+            .lines()
+            .map(|line| {
+                let padding = " ".repeat(width - line.len());
+                format!("{}{padding}", Style::Bold.apply(line))
+            })
+            .collect()
     }
 }
 
@@ -88,6 +136,10 @@ impl Container {
     pub fn new(children: Vec<Box<dyn Element>>) -> Self {
         Container { children }
     }
+
+    pub fn children(&self) -> &[Box<dyn Element>] {
+        &self.children
+    }
 }
 
 impl Element for Container {
@@ -103,23 +155,198 @@ impl Element for Container {
     }
 
     fn render(&self) {
-        let dims = self.dimensions();
-        let render_line = || {
-            println!("+{}+", "-".repeat(dims.width - 2));
-        };
-        render_line();
+        for line in self.render_in_width(self.dimensions().width) {
+            println!("{line}");
+        }
+    }
 
+    fn render_in_width(&self, width: usize) -> Vec<String> {
+        let border = format!("+{}+", "-".repeat(width - 2));
+        let mut lines = vec![border.clone()];
         for child in &self.children {
-            let child_dims = child.dimensions();
-            print!("|");
-            child.render();
-            println!("{}|", " ".repeat(dims.width - 2 - child_dims.width))
+            for line in child.render_in_width(width - 2) {
+                lines.push(format!("|{line}|"));
+            }
+        }
+        lines.push(border);
+        lines
+    }
+}
+
+/// A horizontal bar chart, where each bar is labeled and scaled relative to the largest value.
+pub struct BarChart {
+    bars: Vec<(String, f32)>,
+}
+
+impl BarChart {
+    pub fn new(bars: Vec<(String, f32)>) -> Self {
+        BarChart { bars }
+    }
+
+    fn label_width(&self) -> usize {
+        self.bars
+            .iter()
+            .map(|(label, _)| label.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns each line of the chart, padded to the chart's width.
+    pub fn lines(&self) -> Vec<String> {
+        self.render_in_width(self.dimensions().width)
+    }
+}
+
+impl Element for BarChart {
+    fn dimensions(&self) -> Dimensions {
+        // A label, a space, then a bar of 10 characters between two delimiters
+        Dimensions {
+            width: self.label_width() + 1 + 12,
+            height: self.bars.len(),
         }
+    }
+
+    fn render(&self) {
+        print_lines(&self.lines());
+    }
 
-        render_line();
+    fn render_in_width(&self, width: usize) -> Vec<String> {
+        let label_width = self.label_width();
+        let max = self.bars.iter().map(|(_, value)| *value).fold(0., f32::max);
+        self.bars
+            .iter()
+            .map(|(label, value)| {
+                let mut line = format!("{label:<label_width$} ");
+                let frac = if max > 0. { value / max } else { 0. };
+                week2::p2_design::fill_progress_bar(&mut line, ('[', ']'), frac);
+                format!("{line:<width$}")
+            })
+            .collect()
     }
 }
 
+/// A horizontal line that spans the width of its container.
+pub struct HorizontalRule;
+
+impl Element for HorizontalRule {
+    fn dimensions(&self) -> Dimensions {
+        Dimensions {
+            width: 1,
+            height: 1,
+        }
+    }
+
+    fn render(&self) {
+        print_lines(&self.render_in_width(self.dimensions().width));
+    }
+
+    fn render_in_width(&self, width: usize) -> Vec<String> {
+        vec!["─".repeat(width)]
+    }
+}
+
+/// A given number of blank lines.
+pub struct Spacer(pub usize);
+
+impl Element for Spacer {
+    fn dimensions(&self) -> Dimensions {
+        Dimensions {
+            width: 0,
+            height: self.0,
+        }
+    }
+
+    fn render(&self) {
+        print_lines(&self.render_in_width(self.dimensions().width));
+    }
+
+    fn render_in_width(&self, width: usize) -> Vec<String> {
+        vec![" ".repeat(width); self.0]
+    }
+}
+
+/// A list of items, one of which is selected and drawn in inverse video.
+pub struct SelectableList {
+    items: Vec<String>,
+    selected: usize,
+}
+
+impl SelectableList {
+    /// Creates a list with the first item selected.
+    pub fn new(items: Vec<String>) -> Self {
+        SelectableList { items, selected: 0 }
+    }
+
+    /// Returns the index of the selected item.
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Selects the next item, staying on the last item if it's already selected.
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.items.len().saturating_sub(1));
+    }
+
+    /// Selects the previous item, staying on the first item if it's already selected.
+    pub fn select_prev(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+}
+
+impl Element for SelectableList {
+    fn dimensions(&self) -> Dimensions {
+        Dimensions {
+            width: self.items.iter().map(|item| item.len()).max().unwrap_or(0),
+            height: self.items.len(),
+        }
+    }
+
+    fn render(&self) {
+        print_lines(&self.render_in_width(self.dimensions().width));
+    }
+
+    fn render_in_width(&self, width: usize) -> Vec<String> {
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                // Pad inside the escape codes, so the whole row is highlighted
+                let line = format!("{item:<width$}");
+                if i == self.selected {
+                    Style::Inverse.apply(&line)
+                } else {
+                    line
+                }
+            })
+            .collect()
+    }
+}
+
+/// Parses a small subset of Markdown into a [`Container`]: lines starting with `# ` become
+/// a [`Heading`], each run of blank lines becomes a [`Spacer`], and any other line becomes a [`Text`].
+pub fn parse_markdown(input: &str) -> Container {
+    let mut children: Vec<Box<dyn Element>> = Vec::new();
+    let mut blank_lines = 0;
+    for line in input.lines() {
+        if line.trim().is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+        if blank_lines > 0 {
+            children.push(Box::new(Spacer(blank_lines)));
+            blank_lines = 0;
+        }
+        match line.strip_prefix("# ") {
+            Some(heading) => children.push(Box::new(Heading::new(heading.trim().into()))),
+            None => children.push(Box::new(Text::new(line.into()))),
+        }
+    }
+    if blank_lines > 0 {
+        children.push(Box::new(Spacer(blank_lines)));
+    }
+    Container::new(children)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -130,4 +357,123 @@ mod test {
         let container = Container::new(vec![Box::new(text), Box::new(text2)]);
         container.render();
     }
+
+    #[test]
+    fn bar_chart_test() {
+        let chart = BarChart::new(vec![
+            ("apples".into(), 2.),
+            ("kiwis".into(), 10.),
+            ("figs".into(), 5.),
+        ]);
+        let lines = chart.lines();
+        assert_eq!(
+            lines,
+            vec![
+                "apples [==        ]",
+                "kiwis  [==========]",
+                "figs   [=====     ]",
+            ]
+        );
+
+        let dims = chart.dimensions();
+        assert_eq!((dims.width, dims.height), (19, 3));
+        assert!(lines.iter().all(|line| line.len() == dims.width));
+    }
+
+    #[test]
+    fn rule_test() {
+        let container = Container::new(vec![
+            Box::new(Text::new("hello".into())),
+            Box::new(HorizontalRule),
+            Box::new(Text::new("world!".into())),
+            Box::new(Spacer(1)),
+        ]);
+        let dims = container.dimensions();
+        assert_eq!((dims.width, dims.height), (8, 4));
+        assert_eq!(
+            container.render_in_width(dims.width),
+            vec![
+                "+------+",
+                "|hello |",
+                "|──────|",
+                "|world!|",
+                "|      |",
+                "+------+",
+            ]
+        );
+        container.render();
+    }
+
+    #[test]
+    fn multiline_text_test() {
+        let text = Text::new("first line\nsecond".into());
+        let dims = text.dimensions();
+        assert_eq!((dims.width, dims.height), (10, 2));
+
+        let container = Container::new(vec![
+            Box::new(Heading::new("Title\nBy me".into())),
+            Box::new(text),
+        ]);
+        let dims = container.dimensions();
+        assert_eq!((dims.width, dims.height), (12, 4));
+        assert_eq!(
+            container.render_in_width(dims.width),
+            vec![
+                "+----------+",
+                "|\u{001b}[1mTitle\u{001b}[0m     |",
+                "|\u{001b}[1mBy me\u{001b}[0m     |",
+                "|first line|",
+                "|second    |",
+                "+----------+",
+            ]
+        );
+
+        let empty = Text::new(String::new()).dimensions();
+        assert_eq!((empty.width, empty.height), (0, 1));
+    }
+
+    #[test]
+    fn selectable_list_test() {
+        let mut list = SelectableList::new(vec!["one".into(), "two".into(), "three".into()]);
+        list.select_prev();
+        assert_eq!(list.selected(), 0);
+        for _ in 0..5 {
+            list.select_next();
+        }
+        assert_eq!(list.selected(), 2);
+        assert_eq!(list.render_in_width(5)[2], "\u{001b}[7mthree\u{001b}[0m");
+
+        list.select_prev();
+        assert_eq!(
+            list.render_in_width(6),
+            vec!["one   ", "\u{001b}[7mtwo   \u{001b}[0m", "three "]
+        );
+
+        let dims = list.dimensions();
+        assert_eq!((dims.width, dims.height), (5, 3));
+
+        let mut empty = SelectableList::new(vec![]);
+        empty.select_next();
+        assert_eq!(empty.selected(), 0);
+        assert!(empty.render_in_width(0).is_empty());
+    }
+
+    #[test]
+    fn parse_markdown_test() {
+        let container = parse_markdown("# Groceries\n\n\nEggs and milk");
+        let children = container.children();
+        assert_eq!(children.len(), 3);
+
+        // Headings are the only elements rendered in bold
+        assert_eq!(
+            children[0].render_in_width(9),
+            vec!["\u{001b}[1mGroceries\u{001b}[0m"]
+        );
+        assert_eq!(children[1].render_in_width(2), vec!["  ", "  "]);
+        assert_eq!(children[2].render_in_width(13), vec!["Eggs and milk"]);
+
+        let dims = container.dimensions();
+        assert_eq!((dims.width, dims.height), (15, 4));
+        container.render();
+    }
 }
@@ -0,0 +1,63 @@
+//! Compares computing Fibonacci numbers naively against computing them with a memo.
+//!
+//! Usage: `cargo run --release --bin fib [n]`, where `n` defaults to 35.
+
+use std::env;
+use std::time::Instant;
+use week5::p2_memo::Memo;
+
+/// Computes the `n`-th Fibonacci number with exponentially many recursive calls.
+fn fib_naive(n: u64) -> u64 {
+    if n < 2 {
+        n
+    } else {
+        fib_naive(n - 1) + fib_naive(n - 2)
+    }
+}
+
+/// Computes the `n`-th Fibonacci number with the same recursion as `fib_naive`,
+/// but memoized so each number is only computed once.
+fn fib_memo(n: u64) -> u64 {
+    let fib = Memo::new_recursive(
+        |fib, n: u64| {
+            if n < 2 {
+                n
+            } else {
+                fib(n - 1) + fib(n - 2)
+            }
+        },
+    );
+    *fib.call(n)
+}
+
+fn main() -> Result<(), String> {
+    let n = match env::args().nth(1) {
+        Some(n) => n
+            .parse::<u64>()
+            .map_err(|_| format!("n must be a non-negative integer, got {n:?}"))?,
+        None => 35,
+    };
+
+    let start = Instant::now();
+    let naive = fib_naive(n);
+    println!("naive:    fib({n}) = {naive} in {:?}", start.elapsed());
+
+    let start = Instant::now();
+    let memo = fib_memo(n);
+    println!("memoized: fib({n}) = {memo} in {:?}", start.elapsed());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fib_memo_test() {
+        assert_eq!(fib_memo(30), 832040);
+        assert_eq!(fib_memo(30), fib_naive(30));
+        assert_eq!(fib_memo(0), 0);
+        assert_eq!(fib_memo(90), 2880067194370816120);
+    }
+}
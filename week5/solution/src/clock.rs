@@ -0,0 +1,74 @@
+//! Clocks for time-dependent APIs
+//!
+//! Code that depends on the wall clock is hard to test: tests either have to sleep, which is
+//! slow, or rely on timing, which is flaky. Instead, time-dependent APIs like
+//! [`Memo::with_ttl_and_clock`](crate::p2_memo::Memo::with_ttl_and_clock) read the time
+//! through the [`Clock`] trait. Real code uses [`SystemClock`], and tests can use a
+//! [`MockClock`] that only moves when it is explicitly advanced.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A source of the current time.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// A clock that reads the system's monotonic clock.
+#[derive(Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that is frozen until `advance` is called.
+///
+/// Clones of a `MockClock` share the same time, so a test can keep a clone
+/// to advance a clock that it has given away.
+#[derive(Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl MockClock {
+    pub fn new() -> MockClock {
+        MockClock {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        MockClock::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mock_clock_test() {
+        let clock = MockClock::new();
+        let clock2 = clock.clone();
+        let t1 = clock.now();
+        assert_eq!(clock.now(), t1);
+
+        clock2.advance(Duration::from_secs(5));
+        assert_eq!(clock.now() - t1, Duration::from_secs(5));
+    }
+}
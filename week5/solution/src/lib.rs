@@ -1,2 +1,3 @@
+pub mod clock;
 pub mod p1_context;
 pub mod p2_memo;
@@ -22,16 +22,30 @@
 //! thread-safe, if you need to use interior mutability, you should use a 
 //! [`Mutex`](https://doc.rust-lang.org/std/sync/struct.Mutex.html) instead of a `RefCell`.
 
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
 use std::sync::Mutex;
 
 pub struct Context<T> {
     values: Mutex<Vec<T>>,
+    default: Option<T>,
 }
 
 impl<T: Copy> Context<T> {
     pub fn new() -> Context<T> {
         Context {
             values: Mutex::default(),
+            default: None,
+        }
+    }
+
+    /// Creates a context that holds `default` when no value has been `set`,
+    /// so `get` never returns `None`.
+    pub fn with_default(default: T) -> Context<T> {
+        Context {
+            values: Mutex::default(),
+            default: Some(default),
         }
     }
 
@@ -40,8 +54,30 @@ impl<T: Copy> Context<T> {
         ContextGuard { ctx: self }
     }
 
+    /// Like `set`, but computes the new value from the current one (as returned by `get`).
+    /// The context resets to its previous value when the returned guard is dropped.
+    ///
+    /// The context is locked while `f` runs, so `f` must not access the context itself.
+    pub fn update(&self, f: impl FnOnce(Option<T>) -> T) -> ContextGuard<'_, T> {
+        let mut values = self.values.lock().unwrap();
+        let t = f(values.last().copied().or(self.default));
+        values.push(t);
+        ContextGuard { ctx: self }
+    }
+
     pub fn get(&self) -> Option<T> {
-        self.values.lock().unwrap().last().copied()
+        self.values.lock().unwrap().last().copied().or(self.default)
+    }
+
+    /// Retrieves the latest value of the context, or `default` if it has none.
+    pub fn get_or(&self, default: T) -> T {
+        self.get().unwrap_or(default)
+    }
+
+    /// Returns the number of `set` guards that are currently alive, i.e. how many
+    /// dynamic scopes deep the context is. The default value is not counted.
+    pub fn depth(&self) -> usize {
+        self.values.lock().unwrap().len()
     }
 }
 
@@ -55,6 +91,57 @@ impl<T> Drop for ContextGuard<'_, T> {
     }
 }
 
+impl<T: Copy> Default for Context<T> {
+    fn default() -> Self {
+        Context::new()
+    }
+}
+
+/// A collection of contexts holding at most one value per type, like an ambient environment.
+///
+/// Unlike `Context<T>`, values only need to be `Clone` since `get` returns a copy of the latest value.
+#[derive(Default)]
+pub struct ContextMap {
+    values: Mutex<HashMap<TypeId, Vec<Box<dyn Any + Send>>>>,
+}
+
+impl ContextMap {
+    pub fn new() -> ContextMap {
+        ContextMap::default()
+    }
+
+    pub fn set<T: Any + Send>(&self, t: T) -> ContextMapGuard<'_, T> {
+        self.values
+            .lock()
+            .unwrap()
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .push(Box::new(t));
+        ContextMapGuard {
+            map: self,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn get<T: Any + Clone>(&self) -> Option<T> {
+        let values = self.values.lock().unwrap();
+        let value = values.get(&TypeId::of::<T>())?.last()?;
+        value.downcast_ref::<T>().cloned()
+    }
+}
+
+pub struct ContextMapGuard<'a, T: Any> {
+    map: &'a ContextMap,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Any> Drop for ContextMapGuard<'_, T> {
+    fn drop(&mut self) {
+        let mut values = self.map.values.lock().unwrap();
+        values.get_mut(&TypeId::of::<T>()).unwrap().pop().unwrap();
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -62,6 +149,7 @@ mod test {
 
     lazy_static! {
         static ref CTX: Context<usize> = Context::new();
+        static ref DEFAULT_CTX: Context<usize> = Context::with_default(10);
     }
 
     #[test]
@@ -81,4 +169,85 @@ mod test {
 
         assert_eq!(CTX.get(), Some(0));
     }
+
+    #[test]
+    fn context_get_or_test() {
+        let ctx = Context::new();
+        assert_eq!(ctx.get_or(5), 5);
+
+        let _g = ctx.set(0);
+        assert_eq!(ctx.get_or(5), 0);
+    }
+
+    #[test]
+    fn context_depth_test() {
+        let ctx = Context::with_default(0);
+        assert_eq!(ctx.depth(), 0);
+
+        let g1 = ctx.set(1);
+        assert_eq!(ctx.depth(), 1);
+        {
+            let _g2 = ctx.set(2);
+            assert_eq!(ctx.depth(), 2);
+        }
+        assert_eq!(ctx.depth(), 1);
+
+        drop(g1);
+        assert_eq!(ctx.depth(), 0);
+        assert_eq!(ctx.get(), Some(0));
+    }
+
+    #[test]
+    fn context_default_test() {
+        assert_eq!(DEFAULT_CTX.get(), Some(10));
+
+        {
+            let _g = DEFAULT_CTX.set(0);
+            assert_eq!(DEFAULT_CTX.get(), Some(0));
+            assert_eq!(DEFAULT_CTX.get_or(5), 0);
+        }
+
+        assert_eq!(DEFAULT_CTX.get(), Some(10));
+        assert_eq!(DEFAULT_CTX.get_or(5), 10);
+    }
+
+    #[test]
+    fn context_update_test() {
+        let ctx = Context::new();
+        let incr = |v: Option<usize>| v.unwrap_or(0) + 1;
+        {
+            let _g1 = ctx.update(incr);
+            assert_eq!(ctx.get(), Some(1));
+            {
+                let _g2 = ctx.update(incr);
+                assert_eq!(ctx.get(), Some(2));
+            }
+            assert_eq!(ctx.get(), Some(1));
+        }
+        assert_eq!(ctx.get(), None);
+
+        let ctx = Context::with_default(10);
+        let _g = ctx.update(|v| v.unwrap() * 2);
+        assert_eq!(ctx.get(), Some(20));
+    }
+
+    #[test]
+    fn context_map_test() {
+        let ctx = ContextMap::new();
+        assert_eq!(ctx.get::<usize>(), None);
+
+        let _g1 = ctx.set(0usize);
+        let _g2 = ctx.set(String::from("hello"));
+        assert_eq!(ctx.get::<usize>(), Some(0));
+        assert_eq!(ctx.get::<String>(), Some(String::from("hello")));
+
+        {
+            let _g3 = ctx.set(1usize);
+            assert_eq!(ctx.get::<usize>(), Some(1));
+            assert_eq!(ctx.get::<String>(), Some(String::from("hello")));
+        }
+
+        assert_eq!(ctx.get::<usize>(), Some(0));
+        assert_eq!(ctx.get::<i32>(), None);
+    }
 }
@@ -28,14 +28,65 @@
 //! Note that there is a unit test `memo_scope_test` that is commented out. It *should not compile*.
 //! So you can try commenting it in, and verifying that you get a compiler error.
 
-use std::cell::RefCell;
+use crate::clock::{Clock, SystemClock};
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::hash::Hash;
 use std::pin::Pin;
+use std::time::{Duration, Instant};
 
-pub struct Memo<Func, Input, Output> {
+struct Entry<Input, Output> {
+    // The input that the output was computed from, which may differ from the entry's key.
+    input: Input,
+    output: Pin<Box<Output>>,
+    created: Instant,
+    // The value of the memo's `ticks` when this entry was last returned by `call`.
+    last_used: u64,
+}
+
+type EvictFn<Input, Output> = Box<dyn FnMut(&Input, &Output)>;
+
+/// A function that can be memoized. Every `Fn(Input) -> Output` is a `MemoFn`, as is
+/// a [`Recursive`] function created with [`Memo::new_recursive`].
+pub trait MemoFn<Input, Output> {
+    /// Computes the output for `input`, where `recur` is the memoized function itself.
+    fn compute<'a>(&self, recur: &'a dyn Fn(Input) -> &'a Output, input: Input) -> Output;
+}
+
+impl<F, Input, Output> MemoFn<Input, Output> for F
+where
+    F: Fn(Input) -> Output,
+{
+    fn compute<'a>(&self, _recur: &'a dyn Fn(Input) -> &'a Output, input: Input) -> Output {
+        self(input)
+    }
+}
+
+/// A function that calls its own memoized version for recursive calls.
+pub struct Recursive<F>(F);
+
+impl<F, Input, Output> MemoFn<Input, Output> for Recursive<F>
+where
+    F: for<'a> Fn(&'a dyn Fn(Input) -> &'a Output, Input) -> Output,
+{
+    fn compute<'a>(&self, recur: &'a dyn Fn(Input) -> &'a Output, input: Input) -> Output {
+        (self.0)(recur, input)
+    }
+}
+
+/// A memoized version of the function `func`, whose outputs are cached by a key of type `K`
+/// computed from each input by `KeyFn`. By default, the key is the input itself.
+pub struct Memo<Func, Input, Output, C = SystemClock, K = Input, KeyFn = fn(&Input) -> K> {
     func: Func,
-    cache: RefCell<HashMap<Input, Pin<Box<Output>>>>,
+    key_fn: KeyFn,
+    cache: RefCell<HashMap<K, Entry<Input, Output>>>,
+    ttl: Option<Duration>,
+    capacity: Option<usize>,
+    clock: C,
+    // Outputs replaced after expiring or evicted, kept alive so references to them remain valid.
+    expired: RefCell<Vec<Pin<Box<Output>>>>,
+    ticks: Cell<u64>,
+    on_evict: RefCell<Option<EvictFn<Input, Output>>>,
 }
 
 impl<Func, Input, Output> Memo<Func, Input, Output>
@@ -44,22 +95,245 @@ where
     Func: Fn(Input) -> Output,
 {
     pub fn new(func: Func) -> Self {
+        Memo::base(func, Input::clone, SystemClock)
+    }
+
+    /// Creates a memo that caches at most `capacity` outputs. Once the cache is full, caching
+    /// a new output evicts the least recently used one.
+    ///
+    /// As with [`Memo::with_ttl`], evicted outputs are kept alive until the memo is dropped
+    /// (or [`Memo::clear_expired`] is called), so the capacity bounds the size of the cache
+    /// but not the memory used by the memo.
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn with_capacity(func: Func, capacity: usize) -> Self {
+        assert!(
+            capacity > 0,
+            "a memo must have room for at least one output"
+        );
+        Memo {
+            capacity: Some(capacity),
+            ..Memo::new(func)
+        }
+    }
+
+    /// Creates a memo whose cached outputs are recomputed once they are older than `ttl`.
+    ///
+    /// To uphold the guarantee that references returned by `call` live as long as the memo,
+    /// an expired output is not dropped when it is replaced. It is instead kept alive until
+    /// the memo itself is dropped, so a memo with a TTL grows with every recomputation unless
+    /// [`Memo::clear_expired`] is called.
+    pub fn with_ttl(func: Func, ttl: Duration) -> Self {
+        Memo::with_ttl_and_clock(func, ttl, SystemClock)
+    }
+}
+
+impl<F, Input, Output> Memo<Recursive<F>, Input, Output>
+where
+    Input: Hash + PartialEq + Eq + Clone,
+    F: for<'a> Fn(&'a dyn Fn(Input) -> &'a Output, Input) -> Output,
+{
+    /// Creates a memo for a recursive function. Rather than calling itself directly, `func`
+    /// is given the memoized function as its first argument, so every recursive call is
+    /// cached too. For example:
+    ///
+    /// ```
+    /// # use week5::p2_memo::Memo;
+    /// let fib = Memo::new_recursive(|fib, n: u64| if n < 2 { n } else { fib(n - 1) + fib(n - 2) });
+    /// assert_eq!(*fib.call(50), 12586269025);
+    /// ```
+    pub fn new_recursive(func: F) -> Self {
+        Memo::base(Recursive(func), Input::clone, SystemClock)
+    }
+}
+
+impl<Func, Input, Output, C> Memo<Func, Input, Output, C>
+where
+    Input: Hash + PartialEq + Eq + Clone,
+    Func: Fn(Input) -> Output,
+    C: Clock,
+{
+    /// Like [`Memo::with_ttl`], but the age of cached outputs is measured by `clock`.
+    pub fn with_ttl_and_clock(func: Func, ttl: Duration, clock: C) -> Self {
+        Memo {
+            ttl: Some(ttl),
+            ..Memo::base(func, Input::clone, clock)
+        }
+    }
+}
+
+impl<Func, Input, Output, K, KeyFn> Memo<Func, Input, Output, SystemClock, K, KeyFn>
+where
+    Input: Clone,
+    Func: Fn(Input) -> Output,
+    K: Hash + Eq + Clone,
+    KeyFn: Fn(&Input) -> K,
+{
+    /// Creates a memo that caches outputs by the key `key_fn(&input)` rather than by the
+    /// input itself, so inputs with the same key share one output. For example, a `key_fn`
+    /// of `|s: &String| s.to_lowercase()` makes the memo case-insensitive.
+    ///
+    /// The output for a key is computed from whichever input with that key is called first.
+    pub fn with_key_fn(func: Func, key_fn: KeyFn) -> Self {
+        Memo::with_key_fn_and_clock(func, key_fn, SystemClock)
+    }
+}
+
+impl<Func, Input, Output, C, K, KeyFn> Memo<Func, Input, Output, C, K, KeyFn>
+where
+    Input: Clone,
+    Func: Fn(Input) -> Output,
+    C: Clock,
+    K: Hash + Eq + Clone,
+    KeyFn: Fn(&Input) -> K,
+{
+    /// Like [`Memo::with_key_fn`], but the memo reads the time from `clock`.
+    pub fn with_key_fn_and_clock(func: Func, key_fn: KeyFn, clock: C) -> Self {
+        Memo::base(func, key_fn, clock)
+    }
+}
+
+impl<Func, Input, Output, C, K, KeyFn> Memo<Func, Input, Output, C, K, KeyFn>
+where
+    Input: Clone,
+    Func: MemoFn<Input, Output>,
+    C: Clock,
+    K: Hash + Eq + Clone,
+    KeyFn: Fn(&Input) -> K,
+{
+    /// Creates a memo with an empty cache and no TTL or capacity, which the public
+    /// constructors then customize.
+    fn base(func: Func, key_fn: KeyFn, clock: C) -> Self {
         Memo {
             func,
+            key_fn,
             cache: RefCell::default(),
+            ttl: None,
+            capacity: None,
+            clock,
+            expired: RefCell::default(),
+            ticks: Cell::new(0),
+            on_evict: RefCell::default(),
+        }
+    }
+
+    /// Calls `f` with the input and output of each entry evicted to make room in a memo
+    /// created with [`Memo::with_capacity`], e.g. to record which outputs are no longer cached.
+    ///
+    /// References to an evicted output may still be held, so it isn't dropped after `f` is
+    /// called. It stays alive until the memo is dropped or [`Memo::clear_expired`] is called,
+    /// so resources held by the output are only released then.
+    pub fn on_evict(&mut self, f: impl FnMut(&Input, &Output) + 'static) {
+        *self.on_evict.get_mut() = Some(Box::new(f));
+    }
+
+    /// Drops the outputs that were evicted or replaced after expiring. Since this takes
+    /// `&mut self`, no references returned by `call` can still point to them.
+    pub fn clear_expired(&mut self) {
+        self.expired.get_mut().clear();
+    }
+
+    /// Removes the least recently used entry from `cache`, passing it to the `on_evict` callback.
+    fn evict_lru(&self, cache: &mut HashMap<K, Entry<Input, Output>>) {
+        let Some(lru) = cache
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| key.clone())
+        else {
+            return;
+        };
+        let entry = cache.remove(&lru).unwrap();
+        if let Some(f) = self.on_evict.borrow_mut().as_mut() {
+            f(&entry.input, &entry.output);
+        }
+        self.expired.borrow_mut().push(entry.output);
+    }
+
+    pub fn call(&self, input: Input) -> &Output {
+        let now = self.clock.now();
+        let tick = self.next_tick();
+        let key = (self.key_fn)(&input);
+        if self.is_stale(&key, now) {
+            // The cache must not be borrowed while computing, since a recursive function
+            // calls back into `call`.
+            let output = self.func.compute(&|input| self.call(input), input.clone());
+            self.insert(key.clone(), input, output, now, tick);
         }
+        self.get_cached(&key, tick)
+    }
+
+    fn next_tick(&self) -> u64 {
+        let tick = self.ticks.get();
+        self.ticks.set(tick + 1);
+        tick
     }
 
-    pub fn call<'a>(&'a self, input: Input) -> &'a Output {
+    /// Returns true if `key` has no cached output, or its output has expired.
+    fn is_stale(&self, key: &K, now: Instant) -> bool {
+        match (self.cache.borrow().get(key), self.ttl) {
+            (None, _) => true,
+            (Some(entry), Some(ttl)) => now.duration_since(entry.created) >= ttl,
+            (Some(_), None) => false,
+        }
+    }
+
+    fn insert(&self, key: K, input: Input, output: Output, now: Instant, tick: u64) {
         let mut cache = self.cache.borrow_mut();
-        if !cache.contains_key(&input) {
-            let output = (self.func)(input.clone());
-            cache.insert(input.clone(), Box::pin(output));
+        let entry = Entry {
+            input,
+            output: Box::pin(output),
+            created: now,
+            last_used: tick,
+        };
+        let full = self.capacity.is_some_and(|cap| cache.len() >= cap);
+        if full && !cache.contains_key(&key) {
+            self.evict_lru(&mut cache);
+        }
+        if let Some(old) = cache.insert(key, entry) {
+            self.expired.borrow_mut().push(old.output);
         }
-        let inner_ref = cache[&input].as_ref().get_ref();
+    }
+
+    /// Returns the cached output for `key`, marking it as used at `tick`.
+    fn get_cached<'a>(&'a self, key: &K, tick: u64) -> &'a Output {
+        let mut cache = self.cache.borrow_mut();
+        let entry = cache.get_mut(key).unwrap();
+        entry.last_used = tick;
+        let inner_ref = entry.output.as_ref().get_ref();
         unsafe { std::mem::transmute::<&'_ Output, &'a Output>(inner_ref) }
     }
+}
 
+impl<Func, Input, T, E, C, K, KeyFn> Memo<Func, Input, Result<T, E>, C, K, KeyFn>
+where
+    Input: Clone,
+    Func: MemoFn<Input, Result<T, E>>,
+    C: Clock,
+    K: Hash + Eq + Clone,
+    KeyFn: Fn(&Input) -> K,
+{
+    /// Like `call`, but for a function that can fail. Only successful outputs are cached:
+    /// if the function returns an error, then the error is returned to the caller and the
+    /// function is called again the next time `try_call` is called with the same input.
+    ///
+    /// Note that the recursive calls made by a [`Recursive`] function go through `call`,
+    /// so their errors are cached.
+    pub fn try_call(&self, input: Input) -> Result<&T, E> {
+        let now = self.clock.now();
+        let tick = self.next_tick();
+        let key = (self.key_fn)(&input);
+        let cached_err = || self.cache.borrow()[&key].output.is_err();
+        if self.is_stale(&key, now) || cached_err() {
+            match self.func.compute(&|input| self.call(input), input.clone()) {
+                Ok(output) => self.insert(key.clone(), input, Ok(output), now, tick),
+                Err(e) => return Err(e),
+            }
+        }
+        match self.get_cached(&key, tick) {
+            Ok(output) => Ok(output),
+            Err(_) => unreachable!("errors are never cached by try_call"),
+        }
+    }
 }
 /* END SOLUTION */
 
@@ -109,6 +383,135 @@ mod test {
         assert!(std::ptr::eq(b1, b2))
     }
 
+    #[test]
+    fn memo_ttl_test() {
+        use crate::clock::MockClock;
+        use std::cell::Cell;
+
+        let calls = Cell::new(0);
+        let clock = MockClock::new();
+        let make_bar = Memo::with_ttl_and_clock(
+            |n: usize| {
+                calls.set(calls.get() + 1);
+                make_bar(n)
+            },
+            Duration::from_secs(60),
+            clock.clone(),
+        );
+
+        let b1 = make_bar.call(2);
+        clock.advance(Duration::from_secs(59));
+        let b2 = make_bar.call(2);
+        assert!(std::ptr::eq(b1, b2));
+        assert_eq!(calls.get(), 1);
+
+        clock.advance(Duration::from_secs(1));
+
+        let b3 = make_bar.call(2);
+        assert_eq!(calls.get(), 2);
+        assert!(!std::ptr::eq(b1, b3));
+
+        // The expired output is still valid
+        assert_eq!(b1, "--");
+    }
+
+    #[test]
+    fn memo_on_evict_test() {
+        use std::rc::Rc;
+
+        let evicted = Rc::new(RefCell::new(Vec::new()));
+        let mut make_bar = Memo::with_capacity(make_bar, 2);
+        let evicted_ref = Rc::clone(&evicted);
+        make_bar.on_evict(move |n, bar| evicted_ref.borrow_mut().push((*n, bar.clone())));
+
+        let b1 = make_bar.call(1);
+        make_bar.call(2);
+        // Using 1 again makes 2 the least recently used entry
+        make_bar.call(1);
+        make_bar.call(3);
+        assert_eq!(*evicted.borrow(), vec![(2, "--".to_string())]);
+
+        make_bar.call(4);
+        assert_eq!(evicted.borrow()[1], (1, "-".to_string()));
+
+        // Evicted outputs are still valid, and are recomputed when needed again
+        assert_eq!(b1, "-");
+        assert!(!std::ptr::eq(b1, make_bar.call(1)));
+        assert_eq!(evicted.borrow().len(), 3);
+    }
+
+    #[test]
+    fn memo_clear_expired_test() {
+        use std::rc::Rc;
+
+        let resource = Rc::new(());
+        let resource_ref = Rc::clone(&resource);
+        let mut memo = Memo::with_capacity(move |_: usize| Rc::clone(&resource_ref), 1);
+        memo.call(1);
+        memo.call(2);
+        // Both the cached output and the evicted one are alive
+        assert_eq!(Rc::strong_count(&resource), 4);
+
+        memo.clear_expired();
+        assert_eq!(Rc::strong_count(&resource), 3);
+        assert!(Rc::ptr_eq(memo.call(2), &resource));
+    }
+
+    #[test]
+    fn memo_try_call_test() {
+        use std::cell::Cell;
+
+        // Fails on the first call, then succeeds on every call after that
+        let calls = Cell::new(0);
+        let flaky = Memo::new(|n: usize| {
+            calls.set(calls.get() + 1);
+            if calls.get() == 1 {
+                Err("transient failure")
+            } else {
+                Ok(make_bar(n))
+            }
+        });
+
+        assert_eq!(flaky.try_call(2), Err("transient failure"));
+        let b1 = flaky.try_call(2).unwrap();
+        assert_eq!(b1, "--");
+        assert_eq!(calls.get(), 2);
+
+        let b2 = flaky.try_call(2).unwrap();
+        assert!(std::ptr::eq(b1, b2));
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn memo_key_fn_test() {
+        use crate::clock::MockClock;
+        use std::cell::Cell;
+
+        let calls = Cell::new(0);
+        let shout = Memo::with_key_fn(
+            |s: &str| {
+                calls.set(calls.get() + 1);
+                format!("{}!", s.to_uppercase())
+            },
+            |s: &&str| s.to_lowercase(),
+        );
+        let b1 = shout.call("Hi");
+        let b2 = shout.call("hi");
+        assert_eq!(b1, "HI!");
+        assert!(std::ptr::eq(b1, b2));
+        assert_eq!(calls.get(), 1);
+
+        assert_eq!(shout.call("bye"), "BYE!");
+        assert_eq!(calls.get(), 2);
+
+        // The key function can capture state, and works with any clock
+        let buckets = 10;
+        let make_bar =
+            Memo::with_key_fn_and_clock(make_bar, move |n: &usize| n % buckets, MockClock::new());
+        assert_eq!(make_bar.call(3), "---");
+        assert!(std::ptr::eq(make_bar.call(3), make_bar.call(13)));
+    }
+
     // #[test]
     // fn memo_scope_test() {
     //     let b = {
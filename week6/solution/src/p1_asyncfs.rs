@@ -25,15 +25,19 @@ use std::{
     fs::File,
     future::Future,
     io,
-    marker::PhantomData,
+    marker::{self, PhantomData},
     pin::Pin,
     task::{Context, Poll},
 };
 
 use std::{
-    io::Read,
+    io::{Read, Seek, Write},
     mem,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, TryRecvError},
+        Arc, Mutex,
+    },
     task::Waker,
     thread::{self, JoinHandle},
 };
@@ -45,52 +49,441 @@ pub trait AsyncFile {
     where
         Self: 'a;
 
+    /// The type of the future returned by `write_all_async`.
+    type WriteFuture<'a>: Future<Output = io::Result<()>>
+    where
+        Self: 'a;
+
     /// Asynchronously reads all of a file's contents into a buffer.
     fn read_async<'a>(&'a mut self) -> Self::ReadFuture<'a>;
+
+    /// Like `read_async`, but reads into `buf` (after clearing it) instead of allocating a new
+    /// buffer. The buffer is moved to the thread doing the read, and returned once it's filled.
+    fn read_async_into<'a>(&'a mut self, buf: Vec<u8>) -> Self::ReadFuture<'a>;
+
+    /// Asynchronously writes all of `buf` into a file, in chunks of [`WRITE_CHUNK_SIZE`] bytes.
+    fn write_all_async<'a>(&'a mut self, buf: Vec<u8>) -> Self::WriteFuture<'a>;
+}
+
+/// The number of bytes written at a time by [`AsyncFile::write_all_async`].
+pub const WRITE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A thread that wakes up the task waiting on it when the thread finishes.
+struct Worker<T> {
+    // The flag is true once the thread is done, so a waker stored afterwards is never needed.
+    waker: Arc<Mutex<(bool, Option<Waker>)>>,
+    handle: Option<JoinHandle<T>>,
+}
+
+impl<T: marker::Send + 'static> Worker<T> {
+    fn spawn(f: impl FnOnce() -> T + marker::Send + 'static) -> Self {
+        let waker: Arc<Mutex<(bool, Option<Waker>)>> = Arc::default();
+        let waker_ref = Arc::clone(&waker);
+        let handle = thread::spawn(move || {
+            let output = f();
+            let mut waker = waker_ref.lock().unwrap();
+            waker.0 = true;
+            if let Some(waker) = waker.1.take() {
+                waker.wake();
+            }
+            output
+        });
+        Worker {
+            waker,
+            handle: Some(handle),
+        }
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<T> {
+        let mut waker = self.waker.lock().unwrap();
+        if waker.0 {
+            Poll::Ready(self.handle.take().unwrap().join().unwrap())
+        } else {
+            waker.1 = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+// Dropping a worker before it finishes (e.g. when its future loses a `race`) waits for the
+// thread, since the thread may still be using a file that is only borrowed for the future's lifetime.
+impl<T> Drop for Worker<T> {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Gives a worker thread access to a file that outlives the thread.
+///
+/// # Safety
+/// The returned reference must not be used after `'a` ends. The futures in this module
+/// ensure this by only completing once the thread using the file has finished.
+unsafe fn extend_file<'a>(file: &'a mut File) -> &'static mut File {
+    mem::transmute::<&'a mut File, &'static mut File>(file)
+}
+
+/// Returns the number of bytes left to read in `file`, or 0 if that can't be determined.
+fn remaining_len(file: &mut File) -> usize {
+    let len = file.metadata().map_or(0, |metadata| metadata.len());
+    let pos = file.stream_position().unwrap_or(0);
+    len.saturating_sub(pos) as usize
 }
 
 /// The file reading future.
 pub struct ReadFile<'a> {
-    waker: Arc<Mutex<Option<Waker>>>,
-    handle: Option<JoinHandle<io::Result<Vec<u8>>>>,
+    worker: Worker<io::Result<Vec<u8>>>,
+    capacity: usize,
     _marker: PhantomData<&'a ()>,
 }
 
-// This impl constructs the future when the user calls `file.read_async()`.
+impl ReadFile<'_> {
+    /// Returns the capacity reserved in the buffer before reading, which is the length
+    /// of the rest of the file according to its metadata when the read was started.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// The file writing future.
+pub struct WriteFile<'a> {
+    worker: Worker<io::Result<()>>,
+    progress: Arc<AtomicUsize>,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl WriteFile<'_> {
+    /// Returns a counter of the number of bytes written so far, which increases
+    /// after each chunk is written. The counter can be read while the future is pending.
+    pub fn progress(&self) -> Arc<AtomicUsize> {
+        Arc::clone(&self.progress)
+    }
+}
+
+// This impl constructs the futures when the user calls e.g. `file.read_async()`.
 impl AsyncFile for File {
     type ReadFuture<'a> = ReadFile<'a>;
+    type WriteFuture<'a> = WriteFile<'a>;
 
     fn read_async<'a>(&'a mut self) -> ReadFile<'a> {
-        let file = unsafe { mem::transmute::<&'a mut File, &'static mut File>(self) };
-        let waker: Arc<Mutex<Option<Waker>>> = Arc::new(Mutex::new(None));
-        let waker_ref = Arc::clone(&waker);
-        let handle = thread::spawn(move || {
-            let mut buf = Vec::new();
+        self.read_async_into(Vec::new())
+    }
+
+    fn read_async_into<'a>(&'a mut self, mut buf: Vec<u8>) -> ReadFile<'a> {
+        // Presize the buffer so reading a large file doesn't repeatedly reallocate it
+        let capacity = remaining_len(self);
+        let file = unsafe { extend_file(self) };
+        let worker = Worker::spawn(move || {
+            buf.clear();
+            buf.reserve(capacity);
             file.read_to_end(&mut buf)?;
-            if let Some(waker) = waker_ref.lock().unwrap().take() {
-                waker.wake();
-            }
             Ok(buf)
         });
         ReadFile {
-            waker,
-            handle: Some(handle),
+            worker,
+            capacity,
             _marker: PhantomData,
         }
     }
+
+    fn write_all_async<'a>(&'a mut self, buf: Vec<u8>) -> WriteFile<'a> {
+        let file = unsafe { extend_file(self) };
+        let progress = Arc::new(AtomicUsize::new(0));
+        let progress_ref = Arc::clone(&progress);
+        let worker = Worker::spawn(move || {
+            for chunk in buf.chunks(WRITE_CHUNK_SIZE) {
+                file.write_all(chunk)?;
+                progress_ref.fetch_add(chunk.len(), Ordering::SeqCst);
+            }
+            file.flush()
+        });
+        WriteFile {
+            worker,
+            progress,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// The file copying future.
+pub struct CopyFile<'a> {
+    worker: Worker<io::Result<u64>>,
+    _marker: PhantomData<&'a ()>,
+}
+
+/// Asynchronously copies the remaining contents of `src` into `dst`, returning the number of
+/// bytes copied. Like the other futures, the result cannot outlive either file.
+pub fn copy_async<'a>(src: &'a mut File, dst: &'a mut File) -> CopyFile<'a> {
+    let src = unsafe { extend_file(src) };
+    let dst = unsafe { extend_file(dst) };
+    let worker = Worker::spawn(move || {
+        let n = io::copy(src, dst)?;
+        dst.flush()?;
+        Ok(n)
+    });
+    CopyFile {
+        worker,
+        _marker: PhantomData,
+    }
+}
+
+/// The output of a thread reading one chunk: the file, handed back for the next chunk, and the chunk.
+type ChunkOutput = (&'static mut File, io::Result<Vec<u8>>);
+
+/// A stream of the chunks of a file, created by [`read_chunks`].
+pub struct ReadChunks<'a> {
+    // The file is moved to the worker thread while a chunk is being read, and moved back after.
+    file: Option<&'static mut File>,
+    worker: Option<Worker<ChunkOutput>>,
+    chunk_size: usize,
+    done: bool,
+    _marker: PhantomData<&'a mut File>,
+}
+
+/// Asynchronously reads the remaining contents of `file` in chunks of `chunk_size` bytes.
+/// Every chunk is `chunk_size` bytes long, except for the last one which may be shorter.
+///
+/// Panics if `chunk_size` is 0.
+pub fn read_chunks(file: &mut File, chunk_size: usize) -> ReadChunks<'_> {
+    assert!(chunk_size > 0, "chunks must be at least one byte long");
+    ReadChunks {
+        file: Some(unsafe { extend_file(file) }),
+        worker: None,
+        chunk_size,
+        done: false,
+        _marker: PhantomData,
+    }
+}
+
+impl ReadChunks<'_> {
+    /// Polls for the next chunk, reading it on a worker thread. Like a stream, this returns
+    /// `Some` with each chunk, then `None` once the end of the file is reached. Reading stops
+    /// after the first error.
+    pub fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<io::Result<Vec<u8>>>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+        let chunk_size = this.chunk_size as u64;
+        let worker = this.worker.get_or_insert_with(|| {
+            let file = this.file.take().unwrap();
+            Worker::spawn(move || {
+                let mut buf = Vec::new();
+                let result = (&mut *file).take(chunk_size).read_to_end(&mut buf);
+                (file, result.map(|_| buf))
+            })
+        });
+        let Poll::Ready((file, result)) = worker.poll(cx) else {
+            return Poll::Pending;
+        };
+        this.file = Some(file);
+        this.worker = None;
+        match result {
+            Ok(buf) if buf.is_empty() => {
+                this.done = true;
+                Poll::Ready(None)
+            }
+            Ok(buf) => Poll::Ready(Some(Ok(buf))),
+            Err(e) => {
+                this.done = true;
+                Poll::Ready(Some(Err(e)))
+            }
+        }
+    }
+
+    /// Waits for the next chunk, i.e. the next result of [`ReadChunks::poll_next`].
+    pub async fn next_chunk(&mut self) -> Option<io::Result<Vec<u8>>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
 }
 
-// This impl polls the future for completion, returning the value inside if it's ready.
+/// A stream of the chunks of a file like [`ReadChunks`], but read ahead of the consumer by a
+/// single worker thread. Created by [`read_chunks_bounded`].
+pub struct ReadChunksBounded<'a> {
+    // Only `None` while the stream is being dropped.
+    receiver: Option<mpsc::Receiver<io::Result<Vec<u8>>>>,
+    // The task to wake when the worker sends a chunk or finishes.
+    waker: Arc<Mutex<Option<Waker>>>,
+    chunks_read: Arc<AtomicUsize>,
+    handle: Option<JoinHandle<()>>,
+    done: bool,
+    _marker: PhantomData<&'a mut File>,
+}
+
+/// Asynchronously reads the remaining contents of `file` in chunks of `chunk_size` bytes, like
+/// [`read_chunks`]. Unlike `read_chunks`, the chunks are read ahead on a worker thread while the
+/// consumer processes earlier chunks.
+///
+/// To keep a slow consumer from filling memory with chunks, at most `bound` chunks are buffered.
+/// Once the buffer is full, the worker blocks until the consumer takes a chunk.
+///
+/// Panics if `chunk_size` or `bound` is 0.
+pub fn read_chunks_bounded(
+    file: &mut File,
+    chunk_size: usize,
+    bound: usize,
+) -> ReadChunksBounded<'_> {
+    assert!(chunk_size > 0, "chunks must be at least one byte long");
+    assert!(bound > 0, "at least one chunk must be buffered");
+    let file = unsafe { extend_file(file) };
+    let (sender, receiver) = mpsc::sync_channel(bound);
+    let waker: Arc<Mutex<Option<Waker>>> = Arc::default();
+    let chunks_read: Arc<AtomicUsize> = Arc::default();
+    let (waker_ref, chunks_read_ref) = (Arc::clone(&waker), Arc::clone(&chunks_read));
+    let handle = thread::spawn(move || {
+        let wake = || {
+            if let Some(waker) = waker_ref.lock().unwrap().take() {
+                waker.wake();
+            }
+        };
+        loop {
+            let mut buf = Vec::new();
+            let result = (&mut *file).take(chunk_size as u64).read_to_end(&mut buf);
+            let last = match result {
+                Ok(0) => break,
+                Ok(_) => false,
+                Err(_) => true,
+            };
+            chunks_read_ref.fetch_add(1, Ordering::SeqCst);
+            // This blocks while the buffer is full, and fails once the stream is dropped
+            let sent = sender.send(result.map(|_| buf));
+            wake();
+            if sent.is_err() || last {
+                break;
+            }
+        }
+        // Dropping the sender tells the consumer that there are no more chunks
+        drop(sender);
+        wake();
+    });
+    ReadChunksBounded {
+        receiver: Some(receiver),
+        waker,
+        chunks_read,
+        handle: Some(handle),
+        done: false,
+        _marker: PhantomData,
+    }
+}
+
+impl ReadChunksBounded<'_> {
+    /// Polls for the next chunk, like [`ReadChunks::poll_next`].
+    pub fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<io::Result<Vec<u8>>>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+        // The waker is stored before checking for a chunk, so a chunk sent in between
+        // still wakes the task.
+        *this.waker.lock().unwrap() = Some(cx.waker().clone());
+        match this.receiver.as_ref().unwrap().try_recv() {
+            Ok(Ok(buf)) => Poll::Ready(Some(Ok(buf))),
+            Ok(Err(e)) => {
+                this.done = true;
+                Poll::Ready(Some(Err(e)))
+            }
+            Err(TryRecvError::Empty) => Poll::Pending,
+            Err(TryRecvError::Disconnected) => {
+                this.done = true;
+                Poll::Ready(None)
+            }
+        }
+    }
+
+    /// Waits for the next chunk, i.e. the next result of [`ReadChunksBounded::poll_next`].
+    pub async fn next_chunk(&mut self) -> Option<io::Result<Vec<u8>>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    /// Returns the number of chunks that the worker has read so far, including the chunks
+    /// that have already been taken by the consumer.
+    pub fn chunks_read(&self) -> usize {
+        self.chunks_read.load(Ordering::SeqCst)
+    }
+}
+
+// Like a `Worker`, the thread must finish before the file's borrow ends. Dropping the receiver
+// first unblocks the thread if it's waiting for room in the buffer.
+impl Drop for ReadChunksBounded<'_> {
+    fn drop(&mut self) {
+        self.receiver.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// The output of [`race`], holding the output of whichever future finished first.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Either<L, R> {
+    Left(L),
+    Right(R),
+}
+
+/// The racing future.
+pub struct Race<A, B> {
+    a: Pin<Box<A>>,
+    b: Pin<Box<B>>,
+}
+
+/// Polls two futures concurrently, and resolves with the output of whichever completes first.
+///
+/// The other future is not cancelled: it stays inside the `Race` until the `Race` is dropped.
+/// Dropping one of this module's file futures before it finishes blocks until its worker thread
+/// is done with the file, so once the `Race` is dropped, the current thread blocks until the
+/// losing future's work (e.g. reading the rest of a large file) finishes.
+///
+/// If both futures are ready at the same time, `a` wins.
+pub fn race<A: Future, B: Future>(a: A, b: B) -> Race<A, B> {
+    Race {
+        a: Box::pin(a),
+        b: Box::pin(b),
+    }
+}
+
+// These impls poll the futures for completion, returning the value inside if it's ready.
 impl<'a> Future for ReadFile<'a> {
     type Output = io::Result<Vec<u8>>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        if self.handle.as_ref().unwrap().is_finished() {
-            Poll::Ready(self.handle.take().unwrap().join().unwrap())
-        } else {
-            *self.waker.lock().unwrap() = Some(cx.waker().clone());
-            Poll::Pending
+        self.worker.poll(cx)
+    }
+}
+
+impl<'a> Future for WriteFile<'a> {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.worker.poll(cx)
+    }
+}
+
+impl<A: Future, B: Future> Future for Race<A, B> {
+    type Output = Either<A::Output, B::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(a) = self.a.as_mut().poll(cx) {
+            return Poll::Ready(Either::Left(a));
+        }
+        if let Poll::Ready(b) = self.b.as_mut().poll(cx) {
+            return Poll::Ready(Either::Right(b));
         }
+        Poll::Pending
+    }
+}
+
+impl<'a> Future for CopyFile<'a> {
+    type Output = io::Result<u64>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.worker.poll(cx)
     }
 }
 
@@ -98,6 +491,13 @@ impl<'a> Future for ReadFile<'a> {
 mod test {
     use super::*;
     use std::fs;
+    use std::path::PathBuf;
+
+    /// Returns a path in the temp dir that's unique to this process, so parallel runs
+    /// of the tests don't collide. Each test removes its files once it's done with them.
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}_{name}", std::process::id()))
+    }
 
     #[tokio::test]
     async fn read_test() {
@@ -109,6 +509,189 @@ mod test {
         assert_eq!(String::from_utf8(buf).unwrap(), contents);
     }
 
+    #[tokio::test]
+    async fn read_into_test() {
+        let path1 = temp_path("read_into_test1.txt");
+        let path2 = temp_path("read_into_test2.txt");
+        fs::write(&path1, "hello world").unwrap();
+        fs::write(&path2, "bye").unwrap();
+
+        let buf = Vec::with_capacity(64);
+        let ptr = buf.as_ptr();
+        let mut file = File::open(&path1).unwrap();
+        let buf = file.read_async_into(buf).await.unwrap();
+        assert_eq!(buf, b"hello world");
+
+        // The second read replaces the contents, and reuses the allocation
+        let mut file = File::open(&path2).unwrap();
+        let buf = file.read_async_into(buf).await.unwrap();
+        fs::remove_file(&path1).unwrap();
+        fs::remove_file(&path2).unwrap();
+        assert_eq!(buf, b"bye");
+        assert_eq!(buf.as_ptr(), ptr);
+    }
+
+    #[tokio::test]
+    async fn read_presized_test() {
+        let path = temp_path("read_presized_test.bin");
+        let len = 4 * 1024 * 1024 + 1;
+        let contents = (0..len).map(|i| i as u8).collect::<Vec<_>>();
+        fs::write(&path, &contents).unwrap();
+
+        let mut file = File::open(&path).unwrap();
+        let read = file.read_async();
+        assert_eq!(read.capacity(), contents.len());
+        let buf = read.await.unwrap();
+        assert_eq!(buf, contents);
+        assert!(buf.capacity() >= contents.len());
+
+        // A buffer that already has room for the file is filled without being reallocated
+        let mut file = File::open(&path).unwrap();
+        let buf = Vec::with_capacity(contents.len());
+        let ptr = buf.as_ptr();
+        let buf = file.read_async_into(buf).await.unwrap();
+        assert_eq!(buf, contents);
+        assert_eq!(buf.as_ptr(), ptr);
+
+        // Only the rest of the file is reserved after seeking
+        let mut file = File::open(&path).unwrap();
+        file.seek(io::SeekFrom::Start(1024)).unwrap();
+        let read = file.read_async();
+        assert_eq!(read.capacity(), contents.len() - 1024);
+        let buf = read.await.unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(buf, contents[1024..]);
+    }
+
+    #[tokio::test]
+    async fn read_chunks_test() {
+        const CHUNK_SIZE: usize = 4 * 1024;
+        let path = temp_path("read_chunks_test.bin");
+        let contents = (0..10 * CHUNK_SIZE + 123)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        fs::write(&path, &contents).unwrap();
+
+        let mut file = File::open(&path).unwrap();
+        let mut chunks = read_chunks(&mut file, CHUNK_SIZE);
+        let mut reassembled = Vec::new();
+        let mut lens = Vec::new();
+        while let Some(chunk) = chunks.next_chunk().await {
+            let chunk = chunk.unwrap();
+            lens.push(chunk.len());
+            reassembled.extend(chunk);
+        }
+        assert!(chunks.next_chunk().await.is_none());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(reassembled, contents);
+        assert_eq!(lens.len(), 11);
+        assert!(lens[..10].iter().all(|len| *len == CHUNK_SIZE));
+        assert_eq!(lens[10], 123);
+    }
+
+    #[tokio::test]
+    async fn read_chunks_bounded_test() {
+        const CHUNK_SIZE: usize = 1024;
+        const BOUND: usize = 2;
+        let path = temp_path("read_chunks_bounded_test.bin");
+        let contents = (0..20 * CHUNK_SIZE + 7)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        fs::write(&path, &contents).unwrap();
+
+        let mut file = File::open(&path).unwrap();
+        let mut chunks = read_chunks_bounded(&mut file, CHUNK_SIZE, BOUND);
+        let mut reassembled = Vec::new();
+        let mut taken = 0;
+        while let Some(chunk) = chunks.next_chunk().await {
+            reassembled.extend(chunk.unwrap());
+            taken += 1;
+
+            // Be a slow consumer, giving the worker plenty of time to read ahead. It can only
+            // get `BOUND` chunks ahead, plus the one it's blocked trying to send.
+            thread::sleep(std::time::Duration::from_millis(5));
+            assert!(chunks.chunks_read() <= taken + BOUND + 1);
+        }
+        assert!(chunks.next_chunk().await.is_none());
+        assert_eq!(chunks.chunks_read(), 21);
+        assert_eq!(reassembled, contents);
+
+        // Dropping the stream early stops the blocked worker
+        let mut file = File::open(&path).unwrap();
+        let mut chunks = read_chunks_bounded(&mut file, CHUNK_SIZE, BOUND);
+        assert_eq!(
+            chunks.next_chunk().await.unwrap().unwrap().len(),
+            CHUNK_SIZE
+        );
+        drop(chunks);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn write_all_test() {
+        let path = temp_path("write_all_test.bin");
+        let contents = (0..1024 * 1024).map(|i| i as u8).collect::<Vec<_>>();
+        let mut file = File::create(&path).unwrap();
+
+        let mut write = file.write_all_async(contents.clone());
+        let progress = write.progress();
+        let mut observed = vec![];
+        let result = std::future::poll_fn(|cx| {
+            observed.push(progress.load(Ordering::SeqCst));
+            Pin::new(&mut write).poll(cx)
+        })
+        .await;
+        result.unwrap();
+        observed.push(progress.load(Ordering::SeqCst));
+
+        assert!(observed.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(*observed.last().unwrap(), contents.len());
+        let written = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(written, contents);
+    }
+
+    #[tokio::test]
+    async fn copy_test() {
+        let src_path = temp_path("copy_test_src.bin");
+        let dst_path = temp_path("copy_test_dst.bin");
+        let contents = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        fs::write(&src_path, &contents).unwrap();
+
+        let mut src = File::open(&src_path).unwrap();
+        let mut dst = File::create(&dst_path).unwrap();
+        let n = copy_async(&mut src, &mut dst).await.unwrap();
+        let copied = fs::read(&dst_path).unwrap();
+        fs::remove_file(&src_path).unwrap();
+        fs::remove_file(&dst_path).unwrap();
+        assert_eq!(n, contents.len() as u64);
+        assert_eq!(copied, contents);
+    }
+
+    #[tokio::test]
+    async fn race_test() {
+        let path = temp_path("race_test.txt");
+        fs::write(&path, "hello world").unwrap();
+
+        // A future that never completes always loses, whichever side it's on
+        let mut file = File::open(&path).unwrap();
+        let right = race(std::future::pending::<()>(), file.read_async()).await;
+        let mut file = File::open(&path).unwrap();
+        let left = race(file.read_async(), std::future::pending::<()>()).await;
+        fs::remove_file(&path).unwrap();
+        match right {
+            Either::Right(buf) => assert_eq!(buf.unwrap(), b"hello world"),
+            Either::Left(()) => panic!("pending future won the race"),
+        }
+        match left {
+            Either::Left(buf) => assert_eq!(buf.unwrap(), b"hello world"),
+            Either::Right(()) => panic!("pending future won the race"),
+        }
+
+        let both_ready = race(std::future::ready(1), std::future::ready(2)).await;
+        assert_eq!(both_ready, Either::Left(1));
+    }
+
     // #[tokio::test]
     // async fn read_bad_scope_test() {
     //   fs::write("foo.txt", "hello world").unwrap();
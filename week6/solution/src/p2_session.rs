@@ -4,11 +4,15 @@
 //! For example, this is a session that describes sending a number, receiving a number, and
 //! ending the connection:
 //!
-//!     S = send i32; recv i32; ε
+//! ```text
+//! S = send i32; recv i32; ε
+//! ```
 //!
 //! The "dual" of a session is the corresponding protocol for the opposite side of the connection:
 //!
-//!     dual(S) = recv i32; send i32; ε
+//! ```text
+//! dual(S) = recv i32; send i32; ε
+//! ```
 //!
 //! Below is an API that implements session-typed channels. A user can describe a session type
 //! using the [`Recv`], [`Send`], and [`Close`] structures, e.g. `Send<i32, Recv<i32, Close>>`. The
@@ -33,8 +37,14 @@
 //! <https://doc.rust-lang.org/std/any/index.html#examples>
 
 use std::any::Any;
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, Read, Write};
 use std::marker::{self, PhantomData};
-use std::sync::mpsc;
+use std::net::TcpStream;
+use std::panic;
+use std::sync::{mpsc, Arc};
+use std::thread;
 
 /// Receive a message of type `T`, then change the session to `S`.
 // Note: the `PhantomData` type is needed because Rust will complain if a structure has
@@ -47,9 +57,28 @@ pub struct Send<T, S>(PhantomData<(T, S)>);
 /// Close the session
 pub struct Close;
 
+/// Close the session, confirming that the other end closed it too. Unlike [`Close`], this
+/// detects when the other end was dropped without finishing the session, e.g. because its
+/// thread panicked.
+pub struct CloseAck;
+
 pub struct Choose<S1, S2>(PhantomData<(S1, S2)>);
 pub struct Offer<S1, S2>(PhantomData<(S1, S2)>);
 
+/// Repeat the session `S`, where [`Var`] inside `S` jumps back to the start of `S`.
+///
+/// Only the innermost `Rec` can be jumped to, i.e. a `Var` always refers to the closest
+/// enclosing `Rec`. While inside a `Rec`, the body is remembered in the environment `E` of
+/// the channel's type `Chan<S, E>`.
+pub struct Rec<S>(PhantomData<S>);
+
+/// Jump back to the start of the innermost enclosing [`Rec`].
+pub struct Var;
+
+/// Run the sessions `S1` and `S2` at the same time, e.g. by splitting the channel
+/// with [`Chan::split`] and driving each half from a different thread.
+pub struct Par<S1, S2>(PhantomData<(S1, S2)>);
+
 /// Compute the dual of a session type.
 pub trait HasDual {
     type Dual;
@@ -59,6 +88,10 @@ impl HasDual for Close {
     type Dual = Close;
 }
 
+impl HasDual for CloseAck {
+    type Dual = CloseAck;
+}
+
 impl<T, S: HasDual> HasDual for Recv<T, S> {
     type Dual = Send<T, S::Dual>;
 }
@@ -75,10 +108,102 @@ impl<S1: HasDual, S2: HasDual> HasDual for Offer<S1, S2> {
     type Dual = Choose<S1::Dual, S2::Dual>;
 }
 
-pub struct Chan<S> {
-    sender: mpsc::Sender<Box<dyn Any + marker::Send + 'static>>,
-    receiver: mpsc::Receiver<Box<dyn Any + marker::Send + 'static>>,
-    _marker: PhantomData<S>,
+impl<S1: HasDual, S2: HasDual> HasDual for Par<S1, S2> {
+    type Dual = Par<S1::Dual, S2::Dual>;
+}
+
+impl<S: HasDual> HasDual for Rec<S> {
+    type Dual = Rec<S::Dual>;
+}
+
+impl HasDual for Var {
+    type Dual = Var;
+}
+
+/// Builds a session type from a sequence of steps, e.g. `session!(Send i32; Recv i32; Close)`
+/// is `Send<i32, Recv<i32, Close>>`. The last step is either `Close`, `CloseAck`, `Var`, a repeated session
+/// written as `Rec { .. }`, or a choice between two sessions written as `Offer { .. } { .. }`
+/// or `Choose { .. } { .. }`.
+#[macro_export]
+macro_rules! session {
+    (Close) => { $crate::p2_session::Close };
+    (CloseAck) => { $crate::p2_session::CloseAck };
+    (Var) => { $crate::p2_session::Var };
+    (Rec { $($body:tt)* }) => {
+        $crate::p2_session::Rec<$crate::session!($($body)*)>
+    };
+    (Send $t:ty; $($rest:tt)*) => {
+        $crate::p2_session::Send<$t, $crate::session!($($rest)*)>
+    };
+    (Recv $t:ty; $($rest:tt)*) => {
+        $crate::p2_session::Recv<$t, $crate::session!($($rest)*)>
+    };
+    (Offer { $($left:tt)* } { $($right:tt)* }) => {
+        $crate::p2_session::Offer<$crate::session!($($left)*), $crate::session!($($right)*)>
+    };
+    (Choose { $($left:tt)* } { $($right:tt)* }) => {
+        $crate::p2_session::Choose<$crate::session!($($left)*), $crate::session!($($right)*)>
+    };
+}
+
+/// Names the dual of a session written with [`session!`], e.g. `dual!(Send i32; Close)`
+/// is `Recv<i32, Close>`.
+#[macro_export]
+macro_rules! dual {
+    ($($session:tt)*) => {
+        <$crate::session!($($session)*) as $crate::p2_session::HasDual>::Dual
+    };
+}
+
+/// The kinds of operations on a [`Chan`] that can be logged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    Send,
+    Recv,
+    Offer,
+    Choose,
+}
+
+/// An operation performed on a [`Chan`], passed to the channel's logger.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Event {
+    pub kind: EventKind,
+    /// The name of the type of the message, e.g. `"i32"`. Offers and choices send a `bool`.
+    pub type_name: &'static str,
+}
+
+/// A function that is called with each [`Event`] on a channel.
+pub type Logger = Arc<dyn Fn(Event) + marker::Send + Sync>;
+
+/// A value sent over a [`Chan`], along with the name of its type for debugging.
+struct Message {
+    value: Box<dyn Any + marker::Send + 'static>,
+    type_name: &'static str,
+}
+
+impl Message {
+    fn new<T: marker::Send + 'static>(t: T) -> Self {
+        Message {
+            value: Box::new(t),
+            type_name: std::any::type_name::<T>(),
+        }
+    }
+
+    fn downcast<T: 'static>(self) -> T {
+        *self.value.downcast::<T>().unwrap()
+    }
+}
+
+/// One end of a session-typed channel following the session `S`. The environment `E` holds
+/// the bodies of the enclosing [`Rec`]s, and is `()` outside of any `Rec`.
+pub struct Chan<S, E = ()> {
+    sender: mpsc::Sender<Message>,
+    receiver: mpsc::Receiver<Message>,
+    logger: Option<Logger>,
+    buffer: Option<Vec<Message>>,
+    // A message that has been received by `peek_type_name`, but not yet consumed.
+    peeked: RefCell<Option<Message>>,
+    _marker: PhantomData<(S, E)>,
 }
 
 impl<S: HasDual> Chan<S> {
@@ -89,19 +214,127 @@ impl<S: HasDual> Chan<S> {
             Chan {
                 sender: server_sender,
                 receiver: server_receiver,
+                logger: None,
+                buffer: None,
+                peeked: RefCell::new(None),
                 _marker: PhantomData,
             },
             Chan {
                 sender: client_sender,
                 receiver: client_receiver,
+                logger: None,
+                buffer: None,
+                peeked: RefCell::new(None),
                 _marker: PhantomData,
             },
         )
     }
 }
 
-impl Chan<Close> {
-    pub fn close(self) {}
+impl<S, E> Chan<S, E> {
+    /// Calls `logger` with each operation performed on this end of the channel for the rest
+    /// of the session. This is useful for visualizing how a protocol executes.
+    pub fn with_logger(mut self, logger: impl Fn(Event) + marker::Send + Sync + 'static) -> Self {
+        self.logger = Some(Arc::new(logger));
+        self
+    }
+
+    /// Buffers the messages sent from this end of the channel for the rest of the session, rather
+    /// than passing each one to the other end immediately. Buffered messages (including choices)
+    /// are sent in order before this end next receives a message or offers a choice, and when it closes.
+    pub fn buffered(mut self) -> Self {
+        self.buffer.get_or_insert_with(Vec::new);
+        self
+    }
+
+    fn push(&mut self, msg: Message) {
+        match &mut self.buffer {
+            Some(buffer) => buffer.push(msg),
+            None => self.sender.send(msg).unwrap(),
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(buffer) = &mut self.buffer {
+            for msg in buffer.drain(..) {
+                self.sender.send(msg).unwrap();
+            }
+        }
+    }
+
+    /// Receives the next message, which may have already been received by `peek_type_name`.
+    fn recv_message(&mut self) -> Message {
+        match self.peeked.get_mut().take() {
+            Some(msg) => msg,
+            None => self.receiver.recv().unwrap(),
+        }
+    }
+
+    /// Returns the name of the type of the next message from the other end of the channel,
+    /// without consuming it, or None if no message has arrived yet. This doesn't block.
+    ///
+    /// This is meant for debugging a protocol, e.g. to find out what the other end sent
+    /// when a `recv` would fail. Offers and choices are sent as a `bool`.
+    pub fn peek_type_name(&self) -> Option<&'static str> {
+        let mut peeked = self.peeked.borrow_mut();
+        if peeked.is_none() {
+            *peeked = self.receiver.try_recv().ok();
+        }
+        peeked.as_ref().map(|msg| msg.type_name)
+    }
+
+    fn log<T>(&self, kind: EventKind) {
+        if let Some(logger) = &self.logger {
+            logger(Event {
+                kind,
+                type_name: std::any::type_name::<T>(),
+            });
+        }
+    }
+}
+
+impl<E> Chan<Close, E> {
+    pub fn close(mut self) {
+        self.flush();
+    }
+}
+
+/// The message sent by each end of a [`CloseAck`] session when it closes.
+struct CloseSentinel;
+
+/// The error returned by closing a [`CloseAck`] session when the other end didn't close it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CloseError;
+
+impl fmt::Display for CloseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the other end of the channel was dropped before closing")
+    }
+}
+
+impl std::error::Error for CloseError {}
+
+impl<E> Chan<CloseAck, E> {
+    /// Closes the session like `Chan<Close>::close`, but first tells the other end that this
+    /// end closed cleanly, then waits for the other end to do the same.
+    ///
+    /// Returns an error rather than panicking if the other end was dropped before closing.
+    pub fn close(mut self) -> Result<(), CloseError> {
+        let mut messages = self.buffer.take().unwrap_or_default();
+        messages.push(Message::new(CloseSentinel));
+        for msg in messages {
+            self.sender.send(msg).map_err(|_| CloseError)?;
+        }
+        let msg = match self.peeked.get_mut().take() {
+            Some(msg) => msg,
+            None => self.receiver.recv().map_err(|_| CloseError)?,
+        };
+        if msg.value.is::<CloseSentinel>() {
+            Ok(())
+        } else {
+            Err(CloseError)
+        }
+    }
 }
 
 macro_rules! cast_channel {
@@ -109,47 +342,57 @@ macro_rules! cast_channel {
         Chan {
             sender: $self.sender,
             receiver: $self.receiver,
+            logger: $self.logger,
+            buffer: $self.buffer,
+            peeked: $self.peeked,
             _marker: PhantomData,
         }
     };
 }
 
-impl<T: marker::Send + 'static, S> Chan<Send<T, S>> {
-    pub fn send(self, t: T) -> Chan<S> {
-        self.sender.send(Box::new(t)).unwrap();
+impl<T: marker::Send + 'static, S, E> Chan<Send<T, S>, E> {
+    pub fn send(mut self, t: T) -> Chan<S, E> {
+        self.log::<T>(EventKind::Send);
+        self.push(Message::new(t));
         cast_channel!(self)
     }
 }
 
-impl<T: 'static, S> Chan<Recv<T, S>> {
-    pub fn recv(self) -> (Chan<S>, T) {
-        let t = *self.receiver.recv().unwrap().downcast::<T>().unwrap();
+impl<T: 'static, S, E> Chan<Recv<T, S>, E> {
+    pub fn recv(mut self) -> (Chan<S, E>, T) {
+        self.flush();
+        let t = self.recv_message().downcast::<T>();
+        self.log::<T>(EventKind::Recv);
         let c = cast_channel!(self);
         (c, t)
     }
 }
 
-impl<S1, S2> Chan<Choose<S1, S2>> {
-    pub fn choose_left(self) -> Chan<S1> {
-        self.sender.send(Box::new(false)).unwrap();
+impl<S1, S2, E> Chan<Choose<S1, S2>, E> {
+    pub fn choose_left(mut self) -> Chan<S1, E> {
+        self.log::<bool>(EventKind::Choose);
+        self.push(Message::new(false));
         cast_channel!(self)
     }
 
-    pub fn choose_right(self) -> Chan<S1> {
-        self.sender.send(Box::new(true)).unwrap();
+    pub fn choose_right(mut self) -> Chan<S2, E> {
+        self.log::<bool>(EventKind::Choose);
+        self.push(Message::new(true));
         cast_channel!(self)
     }
 }
 
-pub enum Branch<S1, S2> {
-    Left(Chan<S1>),
-    Right(Chan<S2>),
+pub enum Branch<S1, S2, E = ()> {
+    Left(Chan<S1, E>),
+    Right(Chan<S2, E>),
 }
 
-impl<S1, S2> Chan<Offer<S1, S2>> {
-    pub fn offer(self) -> Branch<S1, S2> {
-        let right = self.receiver.recv().unwrap().downcast::<bool>().unwrap();
-        if *right {
+impl<S1, S2, E> Chan<Offer<S1, S2>, E> {
+    pub fn offer(mut self) -> Branch<S1, S2, E> {
+        self.flush();
+        let right = self.recv_message().downcast::<bool>();
+        self.log::<bool>(EventKind::Offer);
+        if right {
             Branch::Right(cast_channel!(self))
         } else {
             Branch::Left(cast_channel!(self))
@@ -157,6 +400,290 @@ impl<S1, S2> Chan<Offer<S1, S2>> {
     }
 }
 
+impl<S1, S2, E> Chan<Par<S1, S2>, E> {
+    /// Splits the channel into two independent halves. Both ends of the channel must split
+    /// before either can use its halves.
+    ///
+    /// The first half keeps using the original connection. For the second half, each end
+    /// creates a new connection and sends the sending side of it to the other end.
+    pub fn split(mut self) -> (Chan<S1, E>, Chan<S2, E>) {
+        self.flush();
+        let (sender, receiver) = mpsc::channel::<Message>();
+        self.sender.send(Message::new(sender)).unwrap();
+        let peer_sender = self.recv_message().downcast::<mpsc::Sender<Message>>();
+
+        let right = Chan {
+            sender: peer_sender,
+            receiver,
+            logger: self.logger.clone(),
+            buffer: self.buffer.as_ref().map(|_| Vec::new()),
+            peeked: RefCell::new(None),
+            _marker: PhantomData,
+        };
+        (cast_channel!(self), right)
+    }
+}
+
+impl<S, E> Chan<Rec<S>, E> {
+    /// Starts the body of a repeated session, remembering it so that [`Chan::recur`]
+    /// can jump back to the start.
+    pub fn enter(self) -> Chan<S, (S, E)> {
+        cast_channel!(self)
+    }
+}
+
+impl<S, E> Chan<Var, (S, E)> {
+    /// Jumps back to the start of the innermost repeated session.
+    pub fn recur(self) -> Chan<S, (S, E)> {
+        cast_channel!(self)
+    }
+}
+
+/// A session that sends any number of `T`s, one at a time, before continuing with `S`.
+pub type SendStream<T, S> = Rec<SendStreamBody<T, S>>;
+
+/// The dual of [`SendStream`], which receives any number of `T`s before continuing with `S`.
+pub type RecvStream<T, S> = Rec<RecvStreamBody<T, S>>;
+
+// The bodies of the repeated sessions, which are remembered in the environment of the channel.
+type SendStreamBody<T, S> = Choose<Send<T, Var>, S>;
+type RecvStreamBody<T, S> = Offer<Recv<T, Var>, S>;
+type RecvIterState<T, S, E> = Branch<RecvStreamBody<T, S>, S, (RecvStreamBody<T, S>, E)>;
+
+impl<T: marker::Send + 'static, S, E> Chan<SendStream<T, S>, E> {
+    /// Sends every item of `iter`, then tells the peer that the stream has ended.
+    pub fn send_all(self, iter: impl IntoIterator<Item = T>) -> Chan<S, (SendStreamBody<T, S>, E)> {
+        let mut c = self.enter();
+        for t in iter {
+            c = c.choose_left().send(t).recur();
+        }
+        c.choose_right()
+    }
+}
+
+impl<T: 'static, S, E> Chan<RecvStream<T, S>, E> {
+    /// Returns an iterator over the values sent by the peer, which ends once the peer stops
+    /// sending. The rest of the session is available from [`RecvIter::finish`].
+    pub fn recv_iter(self) -> RecvIter<T, S, E> {
+        RecvIter {
+            state: Some(Branch::Left(self.enter())),
+        }
+    }
+}
+
+/// The iterator returned by [`Chan::recv_iter`].
+pub struct RecvIter<T, S, E> {
+    // `Left` while the peer may send more values, and `Right` once it has stopped.
+    // The state is only `None` while it is being advanced.
+    state: Option<RecvIterState<T, S, E>>,
+}
+
+impl<T: 'static, S, E> Iterator for RecvIter<T, S, E> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        match self.state.take() {
+            Some(Branch::Left(c)) => match c.offer() {
+                Branch::Left(c) => {
+                    let (c, t) = c.recv();
+                    self.state = Some(Branch::Left(c.recur()));
+                    Some(t)
+                }
+                Branch::Right(c) => {
+                    self.state = Some(Branch::Right(c));
+                    None
+                }
+            },
+            state => {
+                self.state = state;
+                None
+            }
+        }
+    }
+}
+
+impl<T: 'static, S, E> RecvIter<T, S, E> {
+    /// Receives any values that haven't been iterated over yet, discarding them,
+    /// and returns the channel for the rest of the session.
+    pub fn finish(mut self) -> Chan<S, (RecvStreamBody<T, S>, E)> {
+        while self.next().is_some() {}
+        match self.state {
+            Some(Branch::Right(c)) => c,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The version of a protocol, exchanged by [`Chan::handshake`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Version(pub u32);
+
+/// The reply to a [`Version`] in a handshake, holding the version of the accepting side.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ack(pub Version);
+
+/// A session that starts by checking that both sides use the same version of the protocol.
+/// The version is sent and acknowledged, then the other side chooses to continue with `S`
+/// if the versions match, or to close the session if they don't.
+pub type Handshake<S> = Send<Version, Recv<Ack, Offer<S, Close>>>;
+
+/// The dual of [`Handshake`], i.e. the side that accepts or rejects the version.
+pub type AcceptHandshake<S> = Recv<Version, Send<Ack, Choose<S, Close>>>;
+
+impl<S, E> Chan<Handshake<S>, E> {
+    /// Sends `version` to the other side, and continues with the session if it has the same
+    /// version. Otherwise, the session is closed and the other side's version is returned.
+    pub fn handshake(self, version: Version) -> Result<Chan<S, E>, Version> {
+        let (c, Ack(theirs)) = self.send(version).recv();
+        match c.offer() {
+            Branch::Left(c) => Ok(c),
+            Branch::Right(c) => {
+                c.close();
+                Err(theirs)
+            }
+        }
+    }
+}
+
+impl<S, E> Chan<AcceptHandshake<S>, E> {
+    /// Receives the other side's version and acknowledges it with `version`, then continues
+    /// with the session if they match. Otherwise, the session is closed and the other side's
+    /// version is returned.
+    pub fn accept_handshake(self, version: Version) -> Result<Chan<S, E>, Version> {
+        let (c, theirs) = self.recv();
+        let c = c.send(Ack(version));
+        if theirs == version {
+            Ok(c.choose_left())
+        } else {
+            c.choose_right().close();
+            Err(theirs)
+        }
+    }
+}
+
+/// Creates a pair of dual channels for the session `S`, runs `server_fn` and `client_fn` on
+/// their own threads, and waits for both to finish, returning their results.
+///
+/// The session is inferred from the type of `server_fn`'s argument, e.g.
+/// `run_session(|server: Chan<Session>| ..., |client| ...)`.
+///
+/// If either side panics, then the panic is propagated to the caller once both threads have
+/// finished. (A panic on one side drops its channel, so the other side panics rather than
+/// blocking forever if it's still waiting for a message.) Since either panic could be the
+/// root cause, if both sides panic then the caller's panic reports both messages.
+pub fn run_session<S, F, G, A, B>(server_fn: F, client_fn: G) -> (A, B)
+where
+    S: HasDual + marker::Send,
+    S::Dual: marker::Send,
+    F: FnOnce(Chan<S>) -> A + marker::Send,
+    G: FnOnce(Chan<S::Dual>) -> B + marker::Send,
+    A: marker::Send,
+    B: marker::Send,
+{
+    let (server, client) = Chan::<S>::both();
+    thread::scope(|scope| {
+        let server = scope.spawn(move || server_fn(server));
+        let client = scope.spawn(move || client_fn(client));
+        let (a, b) = (server.join(), client.join());
+        match (a, b) {
+            (Ok(a), Ok(b)) => (a, b),
+            (Err(e), Ok(_)) | (Ok(_), Err(e)) => panic::resume_unwind(e),
+            (Err(server), Err(client)) => panic!(
+                "both sides of the session panicked (server: {}; client: {})",
+                panic_message(&*server),
+                panic_message(&*client)
+            ),
+        }
+    })
+}
+
+/// Returns the message of a panic payload, if it was created from a string by `panic!`.
+fn panic_message(payload: &(dyn Any + marker::Send)) -> &str {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s
+    } else {
+        "<non-string payload>"
+    }
+}
+
+/// Writes `bytes` as a frame: its length as a big-endian `u64`, followed by the bytes themselves.
+pub fn write_frame(w: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    w.write_all(&(bytes.len() as u64).to_be_bytes())?;
+    w.write_all(bytes)?;
+    w.flush()
+}
+
+/// The longest frame [`read_frame`] will accept, in bytes.
+pub const MAX_FRAME_LEN: u64 = 16 << 20;
+
+/// Reads a frame written by [`write_frame`].
+///
+/// A socket may return a frame across several partial reads, so this waits
+/// (via [`Read::read_exact`]) until the entire length prefix and payload have arrived.
+///
+/// The length prefix comes from the peer, so it isn't trusted: a frame longer than
+/// [`MAX_FRAME_LEN`] is rejected with [`io::ErrorKind::InvalidData`] before anything is allocated.
+pub fn read_frame(r: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0; 8];
+    r.read_exact(&mut len)?;
+    let len = u64::from_be_bytes(len);
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {len} bytes exceeds the maximum of {MAX_FRAME_LEN}"),
+        ));
+    }
+    let mut bytes = vec![0; len as usize];
+    r.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// A session-typed channel over a TCP connection.
+///
+/// Unlike [`Chan`], messages have to be serialized to cross the connection, so a `TcpChan` can only
+/// send and receive byte vectors, which are delimited with [`write_frame`] and [`read_frame`].
+pub struct TcpChan<S> {
+    stream: TcpStream,
+    _marker: PhantomData<S>,
+}
+
+impl<S: HasDual> TcpChan<S> {
+    /// Starts a session over `stream`. The peer should start the dual session `TcpChan<S::Dual>`.
+    pub fn new(stream: TcpStream) -> TcpChan<S> {
+        TcpChan {
+            stream,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl TcpChan<Close> {
+    pub fn close(self) {}
+}
+
+impl<S> TcpChan<Send<Vec<u8>, S>> {
+    pub fn send(mut self, bytes: Vec<u8>) -> io::Result<TcpChan<S>> {
+        write_frame(&mut self.stream, &bytes)?;
+        Ok(TcpChan {
+            stream: self.stream,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<S> TcpChan<Recv<Vec<u8>, S>> {
+    pub fn recv(mut self) -> io::Result<(TcpChan<S>, Vec<u8>)> {
+        let bytes = read_frame(&mut self.stream)?;
+        let c = TcpChan {
+            stream: self.stream,
+            _marker: PhantomData,
+        };
+        Ok((c, bytes))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -178,6 +705,347 @@ mod test {
         client.close();
     }
 
+    #[test]
+    fn choose_test() {
+        // The branches have different types, so choosing a branch must continue with that
+        // branch's session rather than the other one.
+        type Session = session!(Choose { Send i32; Close } { Send String; Close });
+        for right in [false, true] {
+            let (server, client) = Chan::<Session>::both();
+            if right {
+                server.choose_right().send(String::from("right")).close();
+            } else {
+                server.choose_left().send(1).close();
+            }
+            match client.offer() {
+                Branch::Left(c) => {
+                    let (c, n) = c.recv();
+                    assert!(!right);
+                    assert_eq!(n, 1);
+                    c.close();
+                }
+                Branch::Right(c) => {
+                    let (c, s) = c.recv();
+                    assert!(right);
+                    assert_eq!(s, "right");
+                    c.close();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn run_session_test() {
+        type Session = Send<i32, Recv<i32, Close>>;
+        let (n2, n) = run_session(
+            |server: Chan<Session>| {
+                let server = server.send(42);
+                let (server, n2) = server.recv();
+                server.close();
+                n2
+            },
+            |client| {
+                let (client, n) = client.recv();
+                let client = client.send(-42);
+                client.close();
+                n
+            },
+        );
+        assert_eq!(n, 42);
+        assert_eq!(n2, -42);
+    }
+
+    #[test]
+    #[should_panic(expected = "client failed")]
+    fn run_session_panic_test() {
+        run_session(
+            |server: Chan<Close>| server.close(),
+            |_client| panic!("client failed"),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "(server: server failed; client: client failed)")]
+    fn run_session_both_panic_test() {
+        run_session(
+            |_server: Chan<Close>| panic!("server failed"),
+            |_client| panic!("client failed"),
+        );
+    }
+
+    #[test]
+    fn close_ack_test() {
+        type Session = session!(Send i32; CloseAck);
+        let (server, client) = Chan::<Session>::both();
+        let client = thread::spawn(move || {
+            let (client, n) = client.recv();
+            assert_eq!(n, 42);
+            client.close()
+        });
+        assert_eq!(server.send(42).close(), Ok(()));
+        assert_eq!(client.join().unwrap(), Ok(()));
+
+        // The client is dropped before it closes, as if its thread had panicked
+        let (server, client) = Chan::<Session>::both();
+        let client = thread::spawn(move || drop(client.recv()));
+        assert_eq!(server.send(42).close(), Err(CloseError));
+        client.join().unwrap();
+    }
+
+    #[test]
+    fn logger_test() {
+        use std::sync::Mutex;
+
+        type Session = Send<i32, Recv<i32, Close>>;
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = |events: &Arc<Mutex<Vec<Event>>>| {
+            let events = Arc::clone(events);
+            move |event| events.lock().unwrap().push(event)
+        };
+        let (server, client) = Chan::<Session>::both();
+        let server = server.with_logger(sink(&events));
+        let client = client.with_logger(sink(&events));
+
+        let server = server.send(42);
+        let (client, _) = client.recv();
+        let client = client.send(-42);
+        let (server, _) = server.recv();
+        server.close();
+        client.close();
+
+        let events = events.lock().unwrap();
+        let count = |kind| events.iter().filter(|e| e.kind == kind).count();
+        assert_eq!(count(EventKind::Send), 2);
+        assert_eq!(count(EventKind::Recv), 2);
+        assert!(events.iter().all(|e| e.type_name == "i32"));
+    }
+
+    #[test]
+    fn buffered_test() {
+        use std::thread;
+
+        type Session = Send<i32, Recv<i32, Close>>;
+        let (server, client) = Chan::<Session>::both();
+        let server = server.buffered();
+        let client = client.buffered();
+
+        // Sends are held back until the server receives
+        let server = server.send(42);
+        assert!(client.receiver.try_recv().is_err());
+
+        let server = thread::spawn(move || {
+            let (server, n) = server.recv();
+            server.close();
+            n
+        });
+        let (client, n) = client.recv();
+        assert_eq!(n, 42);
+        client.send(-42).close();
+        assert_eq!(server.join().unwrap(), -42);
+
+        // Choices stay in order with the data sent around them
+        type Choice = Send<i32, Choose<Send<i32, Recv<i32, Close>>, Close>>;
+        let (server, client) = Chan::<Choice>::both();
+        let server = thread::spawn(move || {
+            let c = server.buffered().send(1).choose_left().send(2);
+            let (c, n) = c.recv();
+            c.close();
+            n
+        });
+        let (c, a) = client.recv();
+        match c.offer() {
+            Branch::Left(c) => {
+                let (c, b) = c.recv();
+                c.send(a + b).close();
+            }
+            Branch::Right(_) => panic!("expected the left branch"),
+        }
+        assert_eq!(server.join().unwrap(), 3);
+    }
+
+    #[test]
+    fn split_test() {
+        use std::thread;
+
+        type Session = Par<Send<i32, Close>, Recv<String, Close>>;
+        let (server, client) = Chan::<Session>::both();
+
+        let client = thread::spawn(move || {
+            let (recv_half, send_half) = client.split();
+            let sender = thread::spawn(move || send_half.send("hello".to_string()).close());
+            let (c, n) = recv_half.recv();
+            c.close();
+            sender.join().unwrap();
+            n
+        });
+
+        // Each half blocks on its own message, so they have to run concurrently
+        let (send_half, recv_half) = server.split();
+        let receiver = thread::spawn(move || {
+            let (c, s) = recv_half.recv();
+            c.close();
+            s
+        });
+        send_half.send(42).close();
+
+        assert_eq!(receiver.join().unwrap(), "hello");
+        assert_eq!(client.join().unwrap(), 42);
+    }
+
+    #[test]
+    fn session_macro_test() {
+        type Session = session!(Send i32; Recv i32; Close);
+        type Dual = dual!(Send i32; Recv i32; Close);
+        let (server, client) = Chan::<Session>::both();
+        let client: Chan<Dual> = client;
+
+        let server = server.send(42);
+        let (client, n) = client.recv();
+        assert_eq!(n, 42);
+
+        let client = client.send(-42);
+        let (server, n2) = server.recv();
+        assert_eq!(n2, -42);
+
+        server.close();
+        client.close();
+
+        type Expected = Offer<Recv<usize, Close>, Close>;
+        let (offer, _) = Chan::<session!(Offer { Recv usize; Close } { Close })>::both();
+        let _: Chan<Expected> = offer;
+    }
+
+    #[test]
+    fn stream_test() {
+        use std::thread;
+
+        type Session = session!(Rec { Choose { Send i32; Var } { Recv usize; Close } });
+        let _: Chan<SendStream<i32, Recv<usize, Close>>> = Chan::<Session>::both().0;
+        let (server, client) = Chan::<Session>::both();
+
+        let values = (0..100).collect::<Vec<i32>>();
+        let sent = values.clone();
+        let server = thread::spawn(move || {
+            let (c, n) = server.send_all(sent).recv();
+            c.close();
+            n
+        });
+
+        let mut iter = client.recv_iter();
+        let received = iter.by_ref().collect::<Vec<_>>();
+        assert_eq!(received, values);
+        assert_eq!(iter.next(), None);
+        iter.finish().send(received.len()).close();
+        assert_eq!(server.join().unwrap(), 100);
+
+        // Finishing early discards the rest of the stream
+        let (server, client) = Chan::<Session>::both();
+        let server = thread::spawn(move || server.send_all(0..10).recv().0.close());
+        let mut iter = client.recv_iter();
+        assert_eq!(iter.next(), Some(0));
+        iter.finish().send(1).close();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn handshake_test() {
+        use std::thread;
+
+        type Session = Handshake<Send<String, Close>>;
+        let (client, server) = Chan::<Session>::both();
+        let server: Chan<AcceptHandshake<Recv<String, Close>>> = server;
+        let server = thread::spawn(move || {
+            let (c, s) = server.accept_handshake(Version(2)).unwrap().recv();
+            c.close();
+            s
+        });
+        let c = client.handshake(Version(2)).unwrap();
+        c.send("hello".into()).close();
+        assert_eq!(server.join().unwrap(), "hello");
+
+        // On a mismatch, each side learns the other's version
+        let (client, server) = Chan::<Session>::both();
+        let server = thread::spawn(move || server.accept_handshake(Version(2)).err());
+        assert_eq!(client.handshake(Version(1)).err(), Some(Version(2)));
+        assert_eq!(server.join().unwrap(), Some(Version(1)));
+    }
+
+    #[test]
+    fn peek_type_name_test() {
+        type Session = Send<i32, Send<String, Close>>;
+        let (server, client) = Chan::<Session>::both();
+        assert_eq!(client.peek_type_name(), None);
+
+        let server = server.send(42);
+        assert_eq!(client.peek_type_name(), Some("i32"));
+        assert_eq!(client.peek_type_name(), Some("i32"));
+        let (client, n) = client.recv();
+        assert_eq!(n, 42);
+
+        server.send("hi".into()).close();
+        assert_eq!(
+            client.peek_type_name(),
+            Some(std::any::type_name::<String>())
+        );
+        let (client, s) = client.recv();
+        assert_eq!(s, "hi");
+        client.close();
+    }
+
+    #[test]
+    fn frame_test() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, b"hello").unwrap();
+        write_frame(&mut buf, b"").unwrap();
+        assert_eq!(buf.len(), 8 + 5 + 8);
+
+        let mut r = &buf[..];
+        assert_eq!(read_frame(&mut r).unwrap(), b"hello");
+        assert_eq!(read_frame(&mut r).unwrap(), b"");
+        assert!(read_frame(&mut r).is_err());
+    }
+
+    #[test]
+    fn frame_too_long_test() {
+        let mut r = &u64::MAX.to_be_bytes()[..];
+        let err = read_frame(&mut r).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut buf = (MAX_FRAME_LEN + 1).to_be_bytes().to_vec();
+        buf.push(0);
+        let err = read_frame(&mut &buf[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn tcp_chan_test() {
+        use std::net::TcpListener;
+        use std::thread;
+
+        type Session = Recv<Vec<u8>, Send<Vec<u8>, Close>>;
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let c = TcpChan::<Session>::new(stream);
+            let (c, mut bytes) = c.recv().unwrap();
+            bytes.reverse();
+            c.send(bytes).unwrap().close();
+        });
+
+        let payload = (0..10 * 1024).map(|i| (i % 256) as u8).collect::<Vec<_>>();
+        let c = TcpChan::<<Session as HasDual>::Dual>::new(TcpStream::connect(addr).unwrap());
+        let c = c.send(payload.clone()).unwrap();
+        let (c, bytes) = c.recv().unwrap();
+        c.close();
+
+        let mut expected = payload;
+        expected.reverse();
+        assert_eq!(bytes, expected);
+        server.join().unwrap();
+    }
+
     #[test]
     #[cfg(feature = "incr-server-test")]
     fn incr_server_test() {
@@ -0,0 +1,97 @@
+//! Combines the async file I/O from P1 with the session-typed channels from P2: an async task
+//! reads a file and streams its contents over a channel to another thread, which writes them back out.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use week6::p1_asyncfs::read_chunks;
+use week6::p2_session::{Branch, Chan, HasDual};
+use week6::session;
+
+/// The number of bytes read from the file and sent over the channel at a time.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The sender repeatedly chooses to send another chunk of the file, until it chooses to end
+/// the stream and receive the number of bytes that were written.
+type Transfer = session!(Rec { Choose { Send Vec<u8>; Var } { Recv usize; Close } });
+
+/// Writes each chunk that arrives on `chan` to `path` as soon as it arrives, returning the
+/// number of chunks received once the sender ends the stream.
+fn receive_file(chan: Chan<<Transfer as HasDual>::Dual>, path: PathBuf) -> usize {
+    let mut file = File::create(path).unwrap();
+    let (mut written, mut chunks) = (0, 0);
+    let mut c = chan.enter();
+    loop {
+        match c.offer() {
+            Branch::Left(next) => {
+                let (next, chunk) = next.recv();
+                file.write_all(&chunk).unwrap();
+                written += chunk.len();
+                chunks += 1;
+                c = next.recur();
+            }
+            Branch::Right(end) => {
+                end.send(written).close();
+                return chunks;
+            }
+        }
+    }
+}
+
+/// Streams the contents of `src` to a receiver thread that writes them to `dst`, returning
+/// the number of bytes the receiver acknowledged writing and the number of chunks it received.
+async fn transfer(src: &Path, dst: &Path) -> (usize, usize) {
+    let (sender, receiver) = Chan::<Transfer>::both();
+    let dst = dst.to_path_buf();
+    let handle = thread::spawn(move || receive_file(receiver, dst));
+
+    let mut file = File::open(src).unwrap();
+    let mut chunks = read_chunks(&mut file, CHUNK_SIZE);
+    let mut c = sender.enter();
+    // Each chunk is sent as soon as it's read, so the receiver can write it while the next is read
+    while let Some(chunk) = chunks.next_chunk().await {
+        c = c.choose_left().send(chunk.unwrap()).recur();
+    }
+    let (c, written) = c.choose_right().recv();
+    c.close();
+
+    (written, handle.join().unwrap())
+}
+
+/// Returns a path in the temp dir that's unique to this process, so parallel runs
+/// of the tests don't collide. Each test removes its files once it's done with them.
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("{}_{name}", std::process::id()))
+}
+
+#[tokio::test]
+async fn file_transfer_test() {
+    let src = temp_path("file_transfer_src.bin");
+    let dst = temp_path("file_transfer_dst.bin");
+    let contents = (0..300_000).map(|i| (i % 253) as u8).collect::<Vec<_>>();
+    fs::write(&src, &contents).unwrap();
+
+    let (written, chunks) = transfer(&src, &dst).await;
+    let received = fs::read(&dst).unwrap();
+    fs::remove_file(&src).unwrap();
+    fs::remove_file(&dst).unwrap();
+    assert_eq!(written, contents.len());
+    assert_eq!(chunks, contents.len().div_ceil(CHUNK_SIZE));
+    assert_eq!(received, contents);
+}
+
+#[tokio::test]
+async fn empty_file_transfer_test() {
+    let src = temp_path("empty_file_transfer_src.bin");
+    let dst = temp_path("empty_file_transfer_dst.bin");
+    fs::write(&src, b"").unwrap();
+
+    let result = transfer(&src, &dst).await;
+    let received = fs::read(&dst).unwrap();
+    fs::remove_file(&src).unwrap();
+    fs::remove_file(&dst).unwrap();
+    assert_eq!(result, (0, 0));
+    assert_eq!(received, b"");
+}
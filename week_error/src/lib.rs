@@ -0,0 +1,71 @@
+//! An error type for the exercises' binaries, so that they can use `?` on I/O, image, and
+//! argument errors alike.
+//!
+//! Conversions from [`io::Error`] and `String` messages are provided, and the `image` feature
+//! adds a conversion from [`image::ImageError`]. Currently only week 3's image loading and saving
+//! return this type. The other exercises keep their own results, e.g. week 6's async reads
+//! return an [`io::Result`] (which `?` converts) and its session channels panic if the other
+//! end disconnects.
+
+use std::{fmt, io};
+
+/// An error from any of the exercises.
+#[derive(Debug)]
+pub enum Error {
+    /// An I/O error, e.g. from reading a file that doesn't exist.
+    Io(io::Error),
+    /// An error decoding or encoding an image.
+    #[cfg(feature = "image")]
+    Image(image::ImageError),
+    /// Any other error, described by a message.
+    Message(String),
+}
+
+/// A result whose error is an [`Error`].
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {e}"),
+            #[cfg(feature = "image")]
+            Error::Image(e) => write!(f, "image error: {e}"),
+            Error::Message(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            #[cfg(feature = "image")]
+            Error::Image(e) => Some(e),
+            Error::Message(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Image errors caused by I/O are converted to [`Error::Io`], so the two
+/// sources of I/O errors can be handled the same way.
+#[cfg(feature = "image")]
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Self {
+        match e {
+            image::ImageError::IoError(e) => Error::Io(e),
+            e => Error::Image(e),
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(msg: String) -> Self {
+        Error::Message(msg)
+    }
+}